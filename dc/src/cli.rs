@@ -0,0 +1,234 @@
+/*
+	headless command line modes, no window is opened
+
+	dc [-e expr].. [-f file] [-s WxH] [-c re,im] [--span s] [--scheme name] [--warp image|checker] [--edge mode] [--f64] [-o out.png]
+	dc --zeros "expr"
+	dc --sphere "expr" mesh.obj|mesh.ply
+*/
+
+use crate::dc;
+use std::path::Path;
+
+const DEFAULT_SIZE : (u32, u32) = (800, 800);
+
+pub const USAGE : &str = "usage: dc [-e expr].. [-f file] [-s WxH] [-c re,im] [--span s] [--scheme name] [--warp image|checker] [--edge mode] [--f64] [-o out.png]
+       dc --zeros \"expr\"
+       dc --sphere \"expr\" mesh.obj|mesh.ply
+schemes: classic phase modulus enhanced cartesian polar
+edges: wrap clamp mirror, warp texture spans -1..1";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchOptions {
+	pub expressions : Vec<String>,
+	pub w 			: u32,
+	pub h 			: u32,
+	pub viewport 	: dc::Viewport,
+	pub scheme 		: dc::ColorScheme,
+	pub output 		: String,
+	pub warp 		: Option<String>, // texture image or 'checker', renders RenderMode::Warp
+	pub edge 		: dc::EdgeMode,
+	pub precision 	: dc::Precision,
+}
+
+impl Default for BatchOptions {
+	fn default() -> Self {
+		Self { expressions : vec![], w : DEFAULT_SIZE.0, h : DEFAULT_SIZE.1, viewport : dc::Viewport::default(),
+			scheme : dc::ColorScheme::default(), output : "dc.png".to_string(),
+			warp : None, edge : dc::EdgeMode::default(), precision : dc::Precision::F32 }
+	}
+}
+
+fn parse_pair(s : &str, sep : char) -> Option<(&str, &str)> {
+	let mut it = s.splitn(2, sep);
+	Some((it.next()?.trim(), it.next()?.trim()))
+}
+
+// expressions in a file, one per line, blank lines and # comments skipped
+pub fn read_expressions(name : &str) -> Result<Vec<String>, String> {
+	let text = std::fs::read_to_string(name).map_err(|err| format!("{}: {}", name, err))?;
+	Ok(text.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')).map(|l| l.to_string()).collect())
+}
+
+impl BatchOptions {
+	pub fn parse(args : &[String]) -> Result<Self, String> {
+		let mut opt = Self::default();
+		let mut it = args.iter();
+
+		while let Some(arg) = it.next() {
+			let mut value = || it.next().ok_or(format!("missing value for {}", arg));
+			match arg.as_str() {
+				"-e" | "--expr"   => opt.expressions.push(value()?.clone()),
+				"-f" | "--file"   => opt.expressions.extend(read_expressions(value()?)?),
+				"-o" | "--output" => opt.output = value()?.clone(),
+				"-s" | "--size"   => {
+					let v = value()?;
+					match parse_pair(v, 'x').map(|(w, h)| (w.parse::<u32>(), h.parse::<u32>())) {
+						Some((Ok(w), Ok(h))) if w > 1 && h > 1 => { opt.w = w; opt.h = h }
+						_ => return Err(format!("bad size '{}', expected WxH", v)),
+					}
+				}
+				"-c" | "--center" => {
+					let v = value()?;
+					match parse_pair(v, ',').map(|(re, im)| (re.parse::<f64>(), im.parse::<f64>())) {
+						Some((Ok(re), Ok(im))) => opt.viewport.center = dc::Cf64::new(re, im),
+						_ => return Err(format!("bad center '{}', expected re,im", v)),
+					}
+				}
+				"--span" => {
+					let v = value()?;
+					match v.parse::<f64>() {
+						Ok(span) if span > 0. => opt.viewport.span = span,
+						_ => return Err(format!("bad span '{}'", v)),
+					}
+				}
+				"--scheme" => {
+					let v = value()?;
+					opt.scheme = dc::ColorScheme::from_name(v).ok_or(format!("unknown scheme '{}'", v))?;
+				}
+				"--warp" => opt.warp = Some(value()?.clone()),
+				"--f64"  => opt.precision = dc::Precision::F64,
+				"--edge" => {
+					let v = value()?;
+					opt.edge = dc::EdgeMode::from_name(v).ok_or(format!("unknown edge mode '{}'", v))?;
+				}
+				_ => return Err(format!("unknown option '{}'", arg)),
+			}
+		}
+		if opt.expressions.is_empty() { return Err("no expression, use -e or -f".to_string()) }
+		Ok(opt)
+	}
+
+	// output name of the n-th expression: out.png, or out_0.png, out_1.png.. for several expressions
+	pub fn output_name(&self, n : usize) -> String {
+		if self.expressions.len() == 1 { return self.output.clone() }
+		let path = Path::new(&self.output);
+		let stem = path.file_stem().map_or("dc".into(), |s| s.to_string_lossy());
+		let ext = path.extension().map_or("png".into(), |s| s.to_string_lossy());
+		path.with_file_name(format!("{}_{}.{}", stem, n, ext)).to_string_lossy().into_owned()
+	}
+
+	// renders all expressions, compile errors are reported and skipped, exit code 1 if any
+	pub fn run(&self) -> i32 {
+		let warp = match self.warp.as_deref() {
+			None 			=> None,
+			Some("checker") => Some(dc::Warp::default()),
+			Some(name) 		=> match dc::Texture::load(name) {
+				Ok(texture) => Some(dc::Warp::new(texture)),
+				Err(err) 	=> { eprintln!("{}: {}", name, err); return 1 }
+			}
+		};
+
+		let mut code = 0;
+		for (n, expr) in self.expressions.iter().enumerate() {
+			match dc::DomainColoring::with_precision(self.w, self.h, expr, self.precision) {
+				Ok(dc) => {
+					let mut dc = dc.with_viewport(self.viewport).with_scheme(self.scheme);
+					if let Some(warp) = &warp {
+						dc = dc.with_mode(dc::RenderMode::Warp).with_warp(warp.clone().with_edge(self.edge));
+					}
+					dc.generate_parallel();
+					let name = self.output_name(n);
+					dc.write_png(&name);
+					println!("{} -> {}", expr, name);
+				}
+				Err(err) => { eprintln!("{}: {}", expr, err); code = 1 }
+			}
+		}
+		code
+	}
+}
+
+// dc --zeros "expr" : table of zeros & poles in the default viewport
+fn print_zeros_poles(expr : &str) -> i32 {
+	match dc::DomainColoring::new(DEFAULT_SIZE.0, DEFAULT_SIZE.1, expr) {
+		Ok(dc) => {
+			println!("zeros & poles of {}\n{:>6} {:>5} {:>12} {:>12}", expr, "kind", "order", "re", "im");
+			for s in dc.zeros_poles() {
+				println!("{:>6} {:>5} {:>12.6} {:>12.6}", s.kind(), s.order.abs(), s.z.re, s.z.im);
+			}
+			0
+		}
+		Err(err) => { eprintln!("{}", err); 1 }
+	}
+}
+
+// dc --sphere "expr" mesh.obj|mesh.ply : riemann sphere mesh + equirectangular png (mesh.png)
+fn write_sphere(expr : &str, name : &str) -> i32 {
+	match dc::DomainColoring::new(2 * DEFAULT_SIZE.0, DEFAULT_SIZE.1, expr) {
+		Ok(dc) => {
+			let mut dc = dc.with_mode(dc::RenderMode::Riemann);
+			if let Err(err) = dc.riemann_sphere(128, 64).write(name) { eprintln!("{}", err); return 1 }
+			dc.generate_parallel();
+			dc.write_png(&Path::new(name).with_extension("png").to_string_lossy());
+			0
+		}
+		Err(err) => { eprintln!("{}", err); 1 }
+	}
+}
+
+// exit code of a command line mode, None: no arguments, open the window
+pub fn run(args : &[String]) -> Option<i32> {
+	match args {
+		[] => None,
+		[mode, expr] if mode == "--zeros" => Some(print_zeros_poles(expr)),
+		[mode, expr, name] if mode == "--sphere" => Some(write_sphere(expr, name)),
+		[help] if help == "-h" || help == "--help" => { println!("{}", USAGE); Some(0) }
+		_ => match BatchOptions::parse(args) {
+			Ok(opt)  => Some(opt.run()),
+			Err(err) => { eprintln!("{}\n{}", err, USAGE); Some(2) }
+		},
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn args(s : &[&str]) -> Vec<String> { s.iter().map(|a| a.to_string()).collect() }
+
+	#[test]
+	fn parse_options() {
+		let opt = BatchOptions::parse(&args(&["-e", "z^2", "-s", "320x200", "-c", "0.5,-1", "--span", "2", "--scheme", "phase", "-o", "out/sq.png"])).unwrap();
+		assert_eq!(opt, BatchOptions { expressions : vec!["z^2".to_string()], w : 320, h : 200,
+			viewport : dc::Viewport::new(dc::Cf64::new(0.5, -1.), 2.), scheme : dc::ColorScheme::Phase, output : "out/sq.png".to_string(),
+			warp : None, edge : dc::EdgeMode::Wrap, precision : dc::Precision::F32 });
+		assert_eq!(opt.output_name(0), "out/sq.png");
+
+		assert!(BatchOptions::parse(&args(&[])).is_err());
+		assert!(BatchOptions::parse(&args(&["-e"])).is_err());
+		assert!(BatchOptions::parse(&args(&["-e", "z", "-s", "10"])).is_err());
+		assert!(BatchOptions::parse(&args(&["-e", "z", "--scheme", "rainbow"])).is_err());
+		assert!(BatchOptions::parse(&args(&["-e", "z", "--bogus"])).is_err());
+		assert!(BatchOptions::parse(&args(&["-e", "z", "--edge", "repeat"])).is_err());
+
+		let opt = BatchOptions::parse(&args(&["-e", "exp(z)", "--warp", "checker", "--edge", "mirror"])).unwrap();
+		assert_eq!((opt.warp.as_deref(), opt.edge), (Some("checker"), dc::EdgeMode::Mirror));
+		assert_eq!(BatchOptions::parse(&args(&["-e", "z", "--f64", "-c", "0.1234567891,0"])).unwrap().precision, dc::Precision::F64);
+	}
+
+	#[test]
+	fn batch_render() {
+		let dir = std::env::temp_dir().join("dc_batch");
+		std::fs::create_dir_all(&dir).unwrap();
+		let list = dir.join("funcs.txt");
+		std::fs::write(&list, "# gallery\nz^2-1\n\nsin(z)/z\n").unwrap();
+
+		let out = dir.join("g.png");
+		let opt = BatchOptions::parse(&args(&["-f", list.to_str().unwrap(), "-s", "40x30", "-o", out.to_str().unwrap()])).unwrap();
+		assert_eq!(opt.expressions, vec!["z^2-1", "sin(z)/z"]);
+		assert_eq!(opt.run(), 0);
+		for n in 0..2 {
+			let png = image::open(opt.output_name(n)).unwrap().to_rgb8();
+			assert_eq!(png.dimensions(), (40, 30));
+		}
+		assert!(opt.output_name(1).ends_with("g_1.png"));
+
+		let bad = BatchOptions::parse(&args(&["-e", "z +", "-e", "z", "-s", "8x8", "-o", out.to_str().unwrap()])).unwrap();
+		assert_eq!(bad.run(), 1);
+		assert_eq!(run(&args(&["-e", "sin(", "-s", "8x8", "-o", out.to_str().unwrap()])), Some(1));
+		assert_eq!(run(&args(&["-x"])), Some(2));
+		assert_eq!(run(&args(&["-e", "z", "--warp", "missing.png", "-o", out.to_str().unwrap()])), Some(1));
+		assert_eq!(run(&args(&["-e", "log(z)", "--warp", "checker", "-s", "16x16", "-o", out.to_str().unwrap()])), Some(0));
+		assert_eq!(run(&[]), None);
+	}
+}
@@ -15,13 +15,156 @@ use druid::{
 };
 
 #[path = "zvm.rs"] mod zvm;
-use zvm::*;
+pub use zvm::*;
+#[path = "riemann.rs"] mod riemann;
+pub use riemann::*;
+#[path = "warp.rs"] mod warp;
+pub use warp::*;
 
 const PI2 : f32 = PI * 2.0;
+const PHI : f32 = 0.618033988_f32;
 
 pub type Cf32 = complex<f32>;
+pub type Cf64 = complex<f64>;
 pub type ZFN = fn(Cf32) -> Cf32;
 
+// window of the complex plane shown in the image, pixels are kept square
+// kept in f64 so deep zooms still resolve pixels, f32 pixel_to_z is the cast of pixel_to_z64
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+	pub center : Cf64,
+	pub span   : f64, // extent of the shortest image side
+}
+
+impl Default for Viewport { fn default() -> Self { Viewport::new(Cf64::new(0., 0.), PI2 as f64) } }
+
+impl Viewport {
+	pub fn new(center : Cf64, span : f64) -> Self {
+		Self { center, span }
+	}
+
+	pub fn scale(&self, w : usize, h : usize) -> f64 { // complex units per pixel
+		self.span / (w.min(h).max(2) - 1) as f64
+	}
+
+	pub fn pixel_to_z64(&self, x : f64, y : f64, w : usize, h : usize) -> Cf64 {
+		let scale = self.scale(w, h);
+		Cf64::new(self.center.re + (x - (w - 1) as f64 / 2.) * scale, 
+				  self.center.im - (y - (h - 1) as f64 / 2.) * scale)
+	}
+
+	pub fn pixel_to_z(&self, x : f32, y : f32, w : usize, h : usize) -> Cf32 {
+		let z = self.pixel_to_z64(x as f64, y as f64, w, h);
+		Cf32::new(z.re as f32, z.im as f32)
+	}
+
+	pub fn zoom(&mut self, factor : f32, x : f32, y : f32, w : usize, h : usize) { // keeps (x,y) fixed
+		let z = self.pixel_to_z64(x as f64, y as f64, w, h);
+		self.center = z + (self.center - z) * factor as f64;
+		self.span *= factor as f64;
+	}
+
+	pub fn z_to_pixel(&self, z : Cf64, w : usize, h : usize) -> (f32, f32) { // inverse of pixel_to_z64
+		let scale = self.scale(w, h);
+		(((z.re - self.center.re) / scale + (w - 1) as f64 / 2.) as f32, 
+		 ((self.center.im - z.im) / scale + (h - 1) as f64 / 2.) as f32)
+	}
+
+	pub fn pan(&mut self, dx : f32, dy : f32, w : usize, h : usize) { // drag image by dx,dy pixels
+		let scale = self.scale(w, h);
+		self.center -= Cf64::new(dx as f64 * scale, -dy as f64 * scale);
+	}
+}
+
+// f closure in the zvm precision, z from the f64 viewport, f(z) as f32 for coloring
+pub enum ZFunc {
+	F32(ZClosure<f32>),
+	F64(ZClosure<f64>),
+}
+
+impl ZFunc {
+	pub fn new(zvm : &ZVm) -> Self {
+		match zvm.precision() {
+			Precision::F32 => ZFunc::F32(zvm.closure()),
+			Precision::F64 => ZFunc::F64(zvm.closure_t()),
+		}
+	}
+
+	pub fn eval(&self, z : Cf64) -> Cf32 {
+		match self {
+			ZFunc::F32(f) => f.eval(Cf32::new(z.re as f32, z.im as f32)),
+			ZFunc::F64(f) => { let v = f.eval(z); Cf32::new(v.re as f32, v.im as f32) }
+		}
+	}
+}
+
+// domain coloring variants, see E. Wegert, Visual Complex Functions
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorScheme {
+	Classic, 		// phase hue, saturation/value modulated by |f| in e-steps
+	Phase, 			// pure phase portrait
+	ModulusContours,// phase hue + log2|f| contour rings
+	EnhancedPhase, 	// phase sectors shaded with modulus rings
+	CartesianGrid, 	// phase portrait with Re f, Im f integer lines
+	PolarGrid, 		// phase portrait with |f| = 2^k and arg f = k*pi/6 lines
+}
+
+impl Default for ColorScheme { fn default() -> Self { ColorScheme::Classic } }
+
+impl ColorScheme {
+	pub const ALL : [ColorScheme; 6] = [ColorScheme::Classic, ColorScheme::Phase, ColorScheme::ModulusContours, 
+		ColorScheme::EnhancedPhase, ColorScheme::CartesianGrid, ColorScheme::PolarGrid];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			ColorScheme::Classic 		 => "classic",
+			ColorScheme::Phase 			 => "phase",
+			ColorScheme::ModulusContours => "modulus",
+			ColorScheme::EnhancedPhase 	 => "enhanced",
+			ColorScheme::CartesianGrid 	 => "cartesian",
+			ColorScheme::PolarGrid 		 => "polar",
+		}
+	}
+
+	pub fn from_name(name : &str) -> Option<Self> {
+		Self::ALL.iter().find(|s| s.name() == name).copied()
+	}
+
+	pub fn next(&self) -> Self {
+		let ix = Self::ALL.iter().position(|s| s == self).unwrap();
+		Self::ALL[(ix + 1) % Self::ALL.len()]
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderMode {
+	Domain, // color f(z) with scheme
+	Newton, // basins of z -> z - f(z)/f'(z), hue by converged root, shade by iterations
+	Riemann,// equirectangular map of the Riemann sphere colored with scheme, top row is infinity
+	Warp, 	// pull back of the warp texture, pixel z shows the texture at f(z)
+}
+
+impl Default for RenderMode { fn default() -> Self { RenderMode::Domain } }
+
+const NEWTON_ITERS : u32 = 64;
+const NEWTON_EPS : f32 = 1e-5;
+const NEWTON_ROOT_EPS : f32 = 1e-3; // roots closer than this are the same root
+
+const REFINE_ITERS : u32 = 8;
+
+// zero (order > 0) or pole (order < 0) of f found in the viewport
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Singularity {
+	pub z 	  : Cf64, // refined in the zvm precision
+	pub order : i32, // winding number of arg f around z
+}
+
+impl Singularity {
+	pub fn is_zero(&self) -> bool { self.order > 0 }
+	pub fn is_pole(&self) -> bool { self.order < 0 }
+	pub fn kind(&self) -> &'static str { if self.is_zero() { "zero" } else { "pole" } }
+}
+
 #[derive(Clone, Debug, Data, Default)]
 pub struct DomainColoring {
 	pub w : u32,
@@ -29,16 +172,91 @@ pub struct DomainColoring {
 	#[data(ignore)] pub image : Vec<u32>,
 	#[data(ignore)] pub image_u8 : Vec<u8>,	
 	#[data(ignore)] pub zvm : ZVm,
+	#[data(ignore)] pub viewport : Viewport,
+	#[data(ignore)] pub scheme : ColorScheme,
+	#[data(ignore)] pub mode : RenderMode,
+	#[data(ignore)] pub markers : bool, // annotate zeros & poles in Domain mode
+	#[data(ignore)] pub warp : Option<Warp>, // texture of Warp mode, a checkerboard once needed if None
 }
 
 
 impl DomainColoring {
-	pub fn new(w : u32, h : u32, zexpr : &str) -> Self {
-		Self{ w, h, image: vec![], image_u8 : vec![], zvm : ZVm::new(zexpr) }
+	pub fn new(w : u32, h : u32, zexpr : &str) -> Result<Self, ZError> {
+		Self::with_precision(w, h, zexpr, Precision::F32)
+	}
+
+	pub fn with_precision(w : u32, h : u32, zexpr : &str, precision : Precision) -> Result<Self, ZError> {
+		Ok(Self{ w, h, image: vec![], image_u8 : vec![], zvm : ZVm::with_precision(zexpr, precision)?, viewport : Viewport::default(), scheme : ColorScheme::default(), mode : RenderMode::default(), markers : false, warp : None })
+	}
+
+	pub fn with_scheme(mut self, scheme : ColorScheme) -> Self {
+		self.scheme = scheme;
+		self
+	}
+
+	pub fn with_viewport(mut self, viewport : Viewport) -> Self {
+		self.viewport = viewport;
+		self
 	}
 			
-	pub fn compile(&mut self, zexpr : &str) {
-		self.zvm = ZVm::new(zexpr);
+	pub fn compile(&mut self, zexpr : &str) -> Result<(), ZError> { // keeps current viewport and expression on error
+		self.zvm = ZVm::with_precision(zexpr, self.zvm.precision())?;
+		self.generate_parallel();
+		Ok(())
+	}
+
+	pub fn set_precision(&mut self, precision : Precision) {
+		self.zvm = ZVm::with_precision(&self.zvm.source, precision).expect("compiled expression should recompile");
+		self.generate_parallel();
+	}
+
+	pub fn set_viewport(&mut self, viewport : Viewport) {
+		self.viewport = viewport;
+		self.generate_parallel();
+	}
+
+	pub fn set_scheme(&mut self, scheme : ColorScheme) {
+		self.scheme = scheme;
+		self.generate_parallel();
+	}
+
+	pub fn with_mode(mut self, mode : RenderMode) -> Self {
+		self.mode = mode;
+		self
+	}
+
+	pub fn set_mode(&mut self, mode : RenderMode) {
+		self.mode = mode;
+		self.generate_parallel();
+	}
+
+	pub fn with_markers(mut self, markers : bool) -> Self {
+		self.markers = markers;
+		self
+	}
+
+	pub fn set_markers(&mut self, markers : bool) {
+		self.markers = markers;
+		self.generate_parallel();
+	}
+
+	pub fn with_warp(mut self, warp : Warp) -> Self {
+		self.warp = Some(warp);
+		self
+	}
+
+	pub fn set_warp(&mut self, warp : Warp) {
+		self.warp = Some(warp);
+		self.generate_parallel();
+	}
+
+	pub fn zoom(&mut self, factor : f32, x : f32, y : f32) {
+		self.viewport.zoom(factor, x, y, self.w as usize, self.h as usize);
+		self.generate_parallel();
+	}
+
+	pub fn pan(&mut self, dx : f32, dy : f32) {
+		self.viewport.pan(dx, dy, self.w as usize, self.h as usize);
 		self.generate_parallel();
 	}
 
@@ -87,23 +305,15 @@ impl DomainColoring {
 		// 0xff00_0000  | (((r * 255_f32) as u32) << 16) | (((g * 255_f32) as u32) << 8) | ((b * 255_f32) as u32)
 	}
 	
-	fn gen_pixel(zvm : &ZVm, index : usize, w : usize, h:usize) -> u32 {
-			
-		let limit = PI;
-		let (rmi, rma, imi, ima) = (-limit, limit, -limit, limit);
-
-		let (i, j) = (index % w,  index / w);
-				
-		let im = ima - (ima - imi) * j as f32 / (h - 1) as f32;
-		let re = rma - (rma - rmi) * i as f32 / (w - 1) as f32;
-
-		let v = zvm.eval(Cf32::new(re, im));
+	fn rgb_2_u32(rgb : [u8; 3]) -> u32 { // inverse of get_pixel_rgb
+		u32::from_be_bytes([rgb[0], rgb[1], rgb[2], 0xff])
+	}
 
-		let mut hue = v.arg(); // calc hue, arg->phase -pi..pi
-		if hue < 0.0_f32 { hue += PI2 }   
-		hue /= PI2;
+	fn sawtooth(x : f32) -> f32 { x - x.floor() }
+	fn dist_int(x : f32) -> f32 { (x - x.round()).abs() } // distance to nearest integer
 
-		let (m, mut ranges,  mut rangee) = (v.norm(),  0_f32, 1_f32);
+	fn classic(hue : f32, m : f32) -> u32 {
+		let (mut ranges,  mut rangee) = (0_f32, 1_f32);
 		while m > rangee {
 			ranges = rangee;
 			rangee *= E;
@@ -116,7 +326,42 @@ impl DomainColoring {
 		let sat : f32 = 0.4_f32 + (1.0_f32 - Self::pow3(1. - kk)) * 0.6_f32;
 		let val : f32 = 0.6_f32 + (1.0_f32 - Self::pow3(1. - (1. - kk))) * 0.4_f32;
 
-		Self::hsv_2_rgb(hue, sat, val)				
+		Self::hsv_2_rgb(hue, sat, val)
+	}
+
+	fn color(scheme : ColorScheme, v : Cf32) -> u32 {
+		const SECTORS : f32 = 12.; // phase sectors & polar grid rays
+		const LINE_WIDTH : f32 = 0.04;
+
+		let mut hue = v.arg(); // calc hue, arg->phase -pi..pi
+		if hue < 0.0_f32 { hue += PI2 }   
+		hue /= PI2;
+
+		let m = v.norm();
+		let log_m = m.log2();
+
+		match scheme {
+			ColorScheme::Classic 		 => Self::classic(hue, m),
+			ColorScheme::Phase 			 => Self::hsv_2_rgb(hue, 1., 1.),
+			ColorScheme::ModulusContours => Self::hsv_2_rgb(hue, 1., 0.6 + 0.4 * Self::sawtooth(log_m)),
+			ColorScheme::EnhancedPhase   => Self::hsv_2_rgb(hue, 1., 
+				(0.7 + 0.3 * Self::sawtooth(log_m)) * (0.7 + 0.3 * Self::sawtooth(hue * SECTORS))),
+			ColorScheme::CartesianGrid   => {
+				let on_line = Self::dist_int(v.re).min(Self::dist_int(v.im)) < LINE_WIDTH;
+				Self::hsv_2_rgb(hue, 1., if on_line { 0.2 } else { 1. })
+			}
+			ColorScheme::PolarGrid 		 => {
+				let on_line = Self::dist_int(log_m).min(Self::dist_int(hue * SECTORS)) < LINE_WIDTH;
+				Self::hsv_2_rgb(hue, 1., if on_line { 0.2 } else { 1. })
+			}
+		}
+	}
+	
+	fn gen_pixel(zf : &ZFunc, vp : &Viewport, scheme : ColorScheme, index : usize, w : usize, h:usize) -> u32 {
+			
+		let (i, j) = (index % w,  index / w);
+
+		Self::color(scheme, zf.eval(vp.pixel_to_z64(i as f64, j as f64, w, h)))
 	}
 
 	pub fn write_png(&self, name : &str) {
@@ -145,20 +390,158 @@ impl DomainColoring {
 		}
 	}
 	
-	pub fn generate_parallel(&mut self) {
-		let (w, h, zvm, size) = (self.w as usize, self.h as usize, self.zvm.clone(), (self.w * self.h) as usize);
+	// newton iteration from z until a step is below eps, converged root & iterations, in the precision of z
+	fn newton<T : ZFloat>(zvm : &ZVm, mut z : complex<T>, eps : T) -> Option<(complex<T>, u32)> {
+		for it in 0..NEWTON_ITERS {
+			let (f, df) = zvm.eval_d_t(z);
+			let dz = f / df;
+			if !dz.re.is_finite() || !dz.im.is_finite() { return None }
+			z = z - dz;
+			if dz.norm() < eps { return Some((z, it)) }
+		}
+		None
+	}
+
+	// newton step & root merging tolerances, f32 ones are fixed, f64 ones a fraction of a pixel so deep zoom roots stay apart
+	fn newton_eps(&self) -> (f64, Option<f64>) {
+		let scale = self.viewport.scale(self.w as usize, self.h as usize);
+		match self.zvm.precision() {
+			Precision::F32 => (NEWTON_EPS as f64, None),
+			Precision::F64 => (scale * 1e-3, Some(scale)),
+		}
+	}
 
-		self.image = (0..size).into_par_iter().map(
-			|index| Self::gen_pixel(&zvm, index, w, h)
+	fn generate_newton(&mut self) {
+		let (w, h, zvm, vp, size) = (self.w as usize, self.h as usize, self.zvm.clone(), self.viewport, (self.w * self.h) as usize);
+		let (eps, root_eps) = self.newton_eps();
+
+		let converged : Vec<Option<(Cf64, u32)>> = (0..size).into_par_iter().map(|index| {
+			let (x, y) = ((index % w) as f64, (index / w) as f64);
+			match zvm.precision() {
+				Precision::F32 => Self::newton(&zvm, vp.pixel_to_z(x as f32, y as f32, w, h), eps as f32).map(|(z, it)| (Cf64::new(z.re as f64, z.im as f64), it)),
+				Precision::F64 => Self::newton(&zvm, vp.pixel_to_z64(x, y, w, h), eps),
+			}
+		}).collect();
+
+		// number roots in scan order
+		let mut roots : Vec<Cf64> = vec![];
+		let root_index : Vec<Option<(usize, u32)>> = converged.iter().map(|c| c.map(|(z, it)| {
+			match roots.iter().position(|r| (r - z).norm() < root_eps.unwrap_or(NEWTON_ROOT_EPS as f64 * (1. + r.norm()))) {
+				Some(ix) => (ix, it),
+				None 	 => { roots.push(z); (roots.len() - 1, it) }
+			}
+		})).collect();
+
+		self.image = root_index.par_iter().map(|ri| match ri {
+			Some((ix, it)) => Self::hsv_2_rgb(Self::sawtooth(*ix as f32 * PHI), 0.8, 1. - 0.8 * (*it as f32 / NEWTON_ITERS as f32).sqrt()),
+			None 		   => Self::hsv_2_rgb(0., 0., 0.),
+		}).collect();
+	}
+
+	// zeros & poles by the argument principle: winding of arg f around each pixel cell,
+	// neighbour cells are merged (higher orders spread spurious +-1 windings around them), 
+	// then refined with multiplicity aware newton steps
+	pub fn zeros_poles(&self) -> Vec<Singularity> {
+		let (w, h, zf, vp, (eps, _)) = (self.w as usize, self.h as usize, ZFunc::new(&self.zvm), self.viewport, self.newton_eps());
+		if w < 2 || h < 2 { return vec![] }
+
+		let f : Vec<Cf32> = (0..w * h).into_par_iter().map(
+			|index| zf.eval(vp.pixel_to_z64((index % w) as f64, (index / w) as f64, w, h))
 		).collect();
+
+		let winding : Vec<i32> = (0..(w - 1) * (h - 1)).into_par_iter().map(|cell| {
+			let (x, y) = (cell % (w - 1), cell / (w - 1));
+			let corners = [f[y * w + x], f[y * w + x + 1], f[(y + 1) * w + x + 1], f[(y + 1) * w + x]];
+			if corners.iter().any(|c| !c.is_finite() || c.norm() == 0.) { return 0 }
+			let turn : f32 = (0..4).map(|i| (corners[(i + 1) % 4] / corners[i]).arg()).sum();
+			-(turn / PI2).round() as i32 // pixel y runs downward: corners are clockwise in z
+		}).collect();
+
+		// merge cells: (sum of pixel centers, cell count, order)
+		let mut clusters : Vec<(f32, f32, f32, i32)> = vec![];
+		let mut last : Vec<(f32, f32, usize)> = vec![]; // last cell position of each cluster
+		for (cell, &wn) in winding.iter().enumerate().filter(|(_, &wn)| wn != 0) {
+			let (x, y) = ((cell % (w - 1)) as f32 + 0.5, (cell / (w - 1)) as f32 + 0.5);
+			match last.iter().position(|&(lx, ly, _)| (lx - x).abs() <= 2. && (ly - y).abs() <= 2.) {
+				Some(l) => {
+					let c = &mut clusters[last[l].2];
+					*c = (c.0 + x, c.1 + y, c.2 + 1., c.3 + wn);
+					last[l].0 = x; last[l].1 = y;
+				}
+				None => {
+					last.push((x, y, clusters.len()));
+					clusters.push((x, y, 1., wn));
+				}
+			}
+		}
+
+		clusters.iter().filter(|c| c.3 != 0).map(|&(sx, sy, n, order)| {
+			let (x, y, radius) = (sx / n, sy / n, 2. * vp.scale(w, h));
+			let z = match self.zvm.precision() {
+				Precision::F32 => { let z = Self::refine(&self.zvm, vp.pixel_to_z(x, y, w, h), order, radius as f32, eps as f32); Cf64::new(z.re as f64, z.im as f64) }
+				Precision::F64 => Self::refine(&self.zvm, vp.pixel_to_z64(x as f64, y as f64, w, h), order, radius, eps),
+			};
+			Singularity { z, order }
+		}).collect()
+	}
+
+	// newton steps z -= order * f/f', converges for zeros and poles of known order, keeps z0 if it leaves the cell
+	fn refine<T : ZFloat>(zvm : &ZVm, z0 : complex<T>, order : i32, radius : T, eps : T) -> complex<T> {
+		let mut z = z0;
+		for _ in 0..REFINE_ITERS {
+			let (f, df) = zvm.eval_d_t(z);
+			let dz = f / df * T::from(order).unwrap();
+			if !dz.re.is_finite() || !dz.im.is_finite() { break }
+			z = z - dz;
+			if dz.norm() < eps * (T::one() + z.norm()) { break }
+		}
+		if (z - z0).norm() <= radius { z } else { z0 }
+	}
+
+	// zeros: white rings, poles: black crosses, size grows with order
+	pub fn draw_markers(&mut self, singularities : &[Singularity]) {
+		let (w, h) = (self.w as i32, self.h as i32);
+		for s in singularities {
+			let (x, y) = self.viewport.z_to_pixel(s.z, w as usize, h as usize);
+			let (r, color) = (3 + 2 * s.order.abs(), Self::hsv_2_rgb(0., 0., if s.is_zero() { 1. } else { 0. }));
+			for dy in -r..=r {
+				for dx in -r..=r {
+					let on = if s.is_zero() { ((dx * dx + dy * dy) as f32).sqrt().round() as i32 == r } else { dx.abs() == dy.abs() };
+					let (px, py) = (x.round() as i32 + dx, y.round() as i32 + dy);
+					if on && px >= 0 && px < w && py >= 0 && py < h {
+						self.image[(py * w + px) as usize] = color;
+					}
+				}
+			}
+		}
+	}
+
+	pub fn generate_parallel(&mut self) {
+		let (w, h, vp, scheme, size) = (self.w as usize, self.h as usize, self.viewport, self.scheme, (self.w * self.h) as usize);
+
+		match self.mode {
+			RenderMode::Domain => {
+				let zf = ZFunc::new(&self.zvm);
+				self.image = (0..size).into_par_iter().map(
+					|index| Self::gen_pixel(&zf, &vp, scheme, index, w, h)
+				).collect()
+			}
+			RenderMode::Newton => self.generate_newton(),
+			RenderMode::Riemann => self.generate_riemann(),
+			RenderMode::Warp => self.generate_warp(),
+		}
+		if self.markers && self.mode == RenderMode::Domain {
+			let singularities = self.zeros_poles();
+			self.draw_markers(&singularities);
+		}
 		self.image_u8 = self.rgb_to_u8();
 	}
 
 	pub fn generate_singleth(&mut self) {
-		let (w, h, zvm, size) = (self.w as usize, self.h as usize, self.zvm.clone(), (self.w * self.h) as usize);
+		let (w, h, zf, vp, scheme, size) = (self.w as usize, self.h as usize, ZFunc::new(&self.zvm), self.viewport, self.scheme, (self.w * self.h) as usize);
 
 		self.image = (0..size).into_iter().map(
-			|index| Self::gen_pixel(&zvm, index, w, h)
+			|index| Self::gen_pixel(&zf, &vp, scheme, index, w, h)
 		).collect();
 	}
 		
@@ -187,4 +570,192 @@ pub fn test_dc() {
 	
 	dc.write_png("dc.png");		
 }
-*/
\ No newline at end of file
+*/
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn newton_roots() { // z^3-1 converges to the cube roots of unity
+		let zvm = ZVm::new("z^3-1").unwrap();
+		for z0 in [Cf32::new(1.3, 0.2), Cf32::new(-0.8, 1.1), Cf32::new(-0.9, -0.7), Cf32::new(0.1, -2.)].iter() {
+			let (root, _) = DomainColoring::newton(&zvm, *z0, NEWTON_EPS).unwrap();
+			assert!((root.powi(3) - 1.).norm() < 1e-4, "{} is not a root", root);
+		}
+		assert_eq!(DomainColoring::newton(&zvm, Cf32::new(0., 0.), NEWTON_EPS), None); // f'(0) = 0
+	}
+
+	#[test]
+	fn zeros_and_poles() { // (z-1)^2 (z+i) / (z+1)^3 / z
+		let dc = DomainColoring::new(160, 120, "(z-1)^2*(z+c(0,1))/(z+1)^3/z").unwrap().with_viewport(Viewport::new(Cf64::new(0.05, 0.1), 4.));
+		let mut found = dc.zeros_poles();
+		found.sort_by(|a, b| a.z.re.partial_cmp(&b.z.re).unwrap());
+
+		let expected = [(Cf64::new(-1., 0.), -3), (Cf64::new(0., -1.), 1), (Cf64::new(0., 0.), -1), (Cf64::new(1., 0.), 2)];
+		assert_eq!(found.len(), expected.len(), "{:?}", found);
+		for (s, (z, order)) in found.iter().zip(expected.iter()) {
+			assert_eq!(s.order, *order, "{:?}", s);
+			assert!((s.z - z).norm() < 1e-3, "{:?} should be at {}", s, z);
+		}
+		assert!(found[3].is_zero() && found[0].is_pole() && found[0].kind() == "pole");
+
+		let mut marked = dc.clone().with_markers(true);
+		marked.generate_parallel();
+		let (x, y) = marked.viewport.z_to_pixel(Cf64::new(1., 0.), 160, 120);
+		let r = 3 + 2 * 2;
+		assert_eq!(marked.image[(y.round() as usize) * 160 + x.round() as usize + r], DomainColoring::hsv_2_rgb(0., 0., 1.));
+	}
+
+	#[test]
+	fn riemann_sphere() {
+		for z in [Cf32::new(0., 0.), Cf32::new(1.5, -0.5), Cf32::new(-20., 7.)].iter() {
+			let p = z_to_sphere(*z);
+			assert!((p[0] * p[0] + p[1] * p[1] + p[2] * p[2] - 1.).abs() < 1e-5);
+			assert!((sphere_to_z(p) - z).norm() < 1e-4 * (1. + z.norm()));
+		}
+		assert!(sphere_to_z([0., 0., 1.]).norm() > 1e5); // north pole is infinity
+
+		// 1/z swaps the hemispheres: (x,y,z) -> (x,-y,-z)
+		let (w, h) = (64, 32);
+		let render = |expr| { 
+			let mut dc = DomainColoring::new(w, h, expr).unwrap().with_scheme(ColorScheme::Phase).with_mode(RenderMode::Riemann);
+			dc.generate_parallel();
+			dc.image
+		};
+		let (direct, inverse) = (render("(z-1)/(z+c(0,2))"), render("(1/z-1)/(1/z+c(0,2))"));
+		let (w, h) = (w as usize, h as usize);
+		let same = (0..w * h).filter(|i| direct[i % w + i / w * w] == inverse[(w - 1 - i % w) % w + (h - 1 - i / w) * w]).count();
+		assert!(same as f32 > 0.95 * (w * h) as f32, "{} of {}", same, w * h);
+
+		let (slices, stacks) = (24, 12);
+		let mesh = DomainColoring::new(w as u32, h as u32, "z").unwrap().riemann_sphere(slices, stacks);
+		assert_eq!(mesh.vertices.len(), (slices + 1) * (stacks + 1));
+		assert_eq!(mesh.faces.len(), 2 * slices * (stacks - 1));
+		assert!(mesh.faces.iter().flatten().all(|&v| (v as usize) < mesh.vertices.len()));
+
+		for ext in ["obj", "ply"].iter() {
+			let name = std::env::temp_dir().join(format!("riemann.{}", ext));
+			mesh.write(name.to_str().unwrap()).unwrap();
+			let text = std::fs::read_to_string(&name).unwrap();
+			let faces = text.lines().filter(|l| l.starts_with(if *ext == "obj" { "f " } else { "3 " })).count();
+			assert_eq!(faces, mesh.faces.len());
+		}
+		assert!(mesh.write("riemann.stl").is_err());
+	}
+
+	#[test]
+	fn warp() {
+		let tex = Texture { w : 3, h : 2, pixels : vec![[0, 0, 0], [100, 0, 0], [200, 0, 0], [0, 50, 0], [100, 50, 0], [200, 50, 10]] };
+		assert_eq!(tex.sample(0.5, 0.5, EdgeMode::Clamp), [50, 25, 0]);
+		assert_eq!(tex.sample(3., 0., EdgeMode::Wrap), [0, 0, 0]);
+		assert_eq!(tex.sample(3., 0., EdgeMode::Clamp), [200, 0, 0]);
+		assert_eq!(tex.sample(3., 0., EdgeMode::Mirror), [200, 0, 0]);
+		assert_eq!(tex.sample(-1., -1., EdgeMode::Mirror), [0, 0, 0]);
+		assert_eq!(tex.sample(4., 2., EdgeMode::Mirror), [100, 50, 0]);
+		assert_eq!(tex.sample(2.5, 0., EdgeMode::Wrap), [100, 0, 0]);
+		assert_eq!(EdgeMode::from_name("mirror"), Some(EdgeMode::Mirror));
+
+		// identity map over the texture's own window reproduces the texture
+		let (w, h) = (48, 32);
+		let vp = Viewport::new(Cf64::new(0.3, -0.2), 1.5);
+		let texture = Texture::checkerboard(w, 6);
+		let texture = Texture { w, h, pixels : texture.pixels[..w * h].iter().enumerate().map(|(i, p)| [p[0], (i % 251) as u8, p[2]]).collect() };
+		let mut dc = DomainColoring::new(w as u32, h as u32, "z").unwrap().with_viewport(vp).with_mode(RenderMode::Warp)
+			.with_warp(Warp::new(texture.clone()).with_source(vp));
+		dc.generate_parallel();
+		for i in 0..w * h { assert_eq!(dc.get_pixel_rgb(i), texture.pixels[i]) }
+
+		dc.compile("1/(z-c(0.3,-0.2))").unwrap(); // pole at the center samples infinity -> black
+		assert!((0..w * h).any(|i| dc.get_pixel_rgb(i) != texture.pixels[i]));
+		assert!(dc.warp.as_ref().unwrap().sample(Cf32::new(f32::INFINITY, 0.)).is_none());
+
+		// default checkerboard, built by the first warp render only
+		let mut dc = DomainColoring::new(8, 8, "z").unwrap();
+		dc.generate_parallel();
+		assert!(dc.warp.is_none());
+		dc.set_mode(RenderMode::Warp);
+		assert_eq!(dc.warp.as_ref().map(|warp| warp.texture.w), Some(512));
+	}
+
+	// f32 deep zoom: pixels collapse onto the same z -> flat bands, f64 resolves them
+	#[test]
+	fn deep_zoom_precision() {
+		let (w, h, c, span) = (64_u32, 64_u32, Cf64::new(1.234567, 0.5), 1e-6);
+		let expr = format!("(z - c({}, {})) * 1e6", c.re, c.im); // unit phase wheel at c
+		let render = |expr : &str, vp, precision| {
+			let mut dc = DomainColoring::with_precision(w, h, expr, precision).unwrap().with_scheme(ColorScheme::Phase).with_viewport(vp);
+			dc.generate_parallel();
+			dc.image
+		};
+		let reference = render("z", Viewport::new(Cf64::new(0., 0.), span * 1e6), Precision::F32);
+		let matching = |image : &Vec<u32>| (0..reference.len()).filter(|&i| image[i] == reference[i]).count() as f32 / reference.len() as f32;
+		let runs = |image : &Vec<u32>| (0..h as usize).map(|y| (1..w as usize).filter(|&x| image[y * w as usize + x] != image[y * w as usize + x - 1]).count()).sum::<usize>();
+
+		let (im32, im64) = (render(&expr, Viewport::new(c, span), Precision::F32), render(&expr, Viewport::new(c, span), Precision::F64));
+		assert!(matching(&im64) > 0.95, "f64 {}", matching(&im64));
+		assert!(matching(&im32) < 0.5, "f32 {}", matching(&im32));
+		assert!(runs(&im32) * 3 < runs(&im64), "color changes f32 {} f64 {}", runs(&im32), runs(&im64));
+
+		let mut dc = DomainColoring::new(w, h, &expr).unwrap().with_viewport(Viewport::new(c, span));
+		dc.set_precision(Precision::F64);
+		assert_eq!(dc.zvm.precision(), Precision::F64);
+		dc.compile("z^2").unwrap();
+		assert_eq!(dc.zvm.precision(), Precision::F64);
+		assert_eq!(ZVm::with_precision("pi", Precision::F64).unwrap().eval64(Cf64::new(0., 0.)).re, std::f64::consts::PI);
+	}
+
+	// newton basins & zeros of a deep zoom: f64 resolves the roots, f32 sees a single one
+	#[test]
+	fn deep_zoom_newton() {
+		let (w, h, c, span) = (64_u32, 64_u32, Cf64::new(1.234567, 0.5), 3e-6);
+		let expr = format!("((z - c({}, {})) * 1e6)^3 - 1", c.re, c.im); // cube roots of unity scaled by 1e-6 around c
+		let render = |expr : &str, vp, precision| {
+			let mut dc = DomainColoring::with_precision(w, h, expr, precision).unwrap().with_viewport(vp).with_mode(RenderMode::Newton);
+			dc.generate_parallel();
+			dc.image
+		};
+		let reference = render("z^3-1", Viewport::new(Cf64::new(0., 0.), span * 1e6), Precision::F64);
+		let matching = |image : &Vec<u32>| (0..reference.len()).filter(|&i| image[i] == reference[i]).count() as f32 / reference.len() as f32;
+
+		let (im32, im64) = (render(&expr, Viewport::new(c, span), Precision::F32), render(&expr, Viewport::new(c, span), Precision::F64));
+		assert!(matching(&im64) > 0.95, "f64 {}", matching(&im64));
+		assert!(matching(&im32) < 0.5, "f32 {}", matching(&im32));
+
+		let dc = DomainColoring::with_precision(w, h, &expr, Precision::F64).unwrap().with_viewport(Viewport::new(c, span));
+		let zeros = dc.zeros_poles();
+		assert_eq!(zeros.len(), 3, "{:?}", zeros);
+		for s in zeros.iter() {
+			let r = (s.z - c) * 1e6;
+			assert!(s.order == 1 && (r.powi(3) - 1.).norm() < 1e-4, "{:?}", s);
+			let root = c + Cf64::from_polar(1e-6, (r.arg() / (std::f64::consts::PI * 2. / 3.)).round() * std::f64::consts::PI * 2. / 3.);
+			let pixel = |z| { let (x, y) = dc.viewport.z_to_pixel(z, w as usize, h as usize); (x.round(), y.round()) };
+			assert_eq!(pixel(s.z), pixel(root), "marker of {:?}", s); // f32 z is off by pixels
+		}
+	}
+
+	#[test]
+	fn schemes_reproducible() { // same scheme -> same png, schemes differ
+		let mut images : Vec<Vec<u32>> = vec![];
+
+		for scheme in ColorScheme::ALL.iter() {
+			let mut dc = DomainColoring::new(64, 48, "(z^2-1)/(z^2+1)").unwrap().with_scheme(*scheme);
+			dc.generate_parallel();
+
+			let name = std::env::temp_dir().join(format!("dc_{}.png", scheme.name()));
+			dc.write_png(name.to_str().unwrap());
+			let png = image::open(&name).unwrap().to_rgb8();
+			for (x, y, pixel) in png.enumerate_pixels() {
+				assert_eq!(pixel.0, dc.get_pixel_rgb((y * dc.w + x) as usize));
+			}
+
+			let image = dc.image.clone();
+			dc.generate_parallel();
+			assert_eq!(image, dc.image);
+
+			assert!(images.iter().all(|im| *im != image), "{} duplicates another scheme", scheme.name());
+			assert_eq!(ColorScheme::from_name(scheme.name()), Some(*scheme));
+			images.push(image);
+		}
+	}
+}
@@ -13,7 +13,7 @@ use druid::widget::prelude::*;
 use druid::im::{vector, Vector};
 use druid::{
 	commands,   AppDelegate, AppLauncher, Command, Data, DelegateCtx, RenderContext, ExtEventSink, Handled, Lens, LensExt,
-	Selector, Target, WidgetExt, WindowDesc, Application, TimerToken, FileDialogOptions, FileSpec, UnitPoint, Rect,
+	Selector, Target, WidgetExt, WindowDesc, Application, TimerToken, FileDialogOptions, FileSpec, UnitPoint, Rect, Point, Color,
 	piet::{ImageBuf, ImageFormat, InterpolationMode},
 	widget::{Flex, Label, Align, Button, Stepper, TextBox,  LensWrap, Parse, Image, Either,  List, Scroll},
 };
@@ -24,8 +24,10 @@ use image;
 const WINDOW_SIZE : (f64, f64) = (800., 800.);
 const DC_SIZE : u32 = WINDOW_SIZE.0  as u32;
 const START_PREDEF_FUNC : f64 = 18.;
+const ZOOM_STEP : f32 = 1.25;
 	
 mod dc;
+mod cli;
 
 
 const PREDEF_FUNCS : [&str; 19] = [
@@ -43,14 +45,35 @@ struct UI {
 	stepper_value : f64,
 	update		  : bool,
 	status		  : String,
+	error		  : String,
+	#[data(ignore)] drag_from : Option<Point>,
 }
 
 impl UI {
 	fn new(w : u32, h : u32, func : &str) -> Self {
 		 Self { 
-			dc : dc::DomainColoring::new(w, h, func), 
-			expression : func.to_string() , stepper_value : START_PREDEF_FUNC, update:true, status : "".to_string() }
+			dc : dc::DomainColoring::new(w, h, func).expect("predefined function should compile"), 
+			expression : func.to_string() , stepper_value : START_PREDEF_FUNC, update:true, status : "".to_string(), error : "".to_string(), drag_from : None }
 	}
+
+	fn compile(&mut self) { // on error keep last image and show message
+		match self.dc.compile(&self.expression) {
+			Ok(())   => { self.error.clear(); self.status = "compiled expression: ok".to_string() }
+			Err(err) => { self.error = format!("{}", err); self.status = "compiled expression: error".to_string() }
+		}
+		self.update =! self.update; // trigger update
+	}
+
+	fn viewport_status(&mut self) {
+		let vp = self.dc.viewport;
+		self.status = format!("center: {:.6} {:+.6}i, span: {:.3e}", vp.center.re, vp.center.im, vp.span);
+		self.update =! self.update; // trigger update
+	}
+}
+
+// widget coords -> dc image pixel coords
+fn to_image_pos(size : Size, pos : Point) -> (f32, f32) {
+	((pos.x * DC_SIZE as f64 / size.width) as f32, (pos.y * DC_SIZE as f64 / size.height) as f32)
 }
 
 impl Widget<UI> for dc::DomainColoring {
@@ -61,6 +84,31 @@ impl Widget<UI> for dc::DomainColoring {
 				ctx.set_focus(ctx.widget_id());
 				ctx.request_focus() 		// support key stroke               
 			}
+			Event::Wheel(me) => { // zoom around mouse position
+				let (x, y) = to_image_pos(ctx.size(), me.pos);
+				_ui.dc.zoom(if me.wheel_delta.y > 0. { ZOOM_STEP } else { 1. / ZOOM_STEP }, x, y);
+				_ui.viewport_status();
+				ctx.request_paint();
+			}
+			Event::MouseDown(me) => {
+				ctx.set_active(true);
+				_ui.drag_from = Some(me.pos);
+			}
+			Event::MouseMove(me) => { // drag pan
+				if ctx.is_active() {
+					if let Some(from) = _ui.drag_from {
+						let ((x0, y0), (x1, y1)) = (to_image_pos(ctx.size(), from), to_image_pos(ctx.size(), me.pos));
+						_ui.dc.pan(x1 - x0, y1 - y0);
+						_ui.drag_from = Some(me.pos);
+						_ui.viewport_status();
+						ctx.request_paint();
+					}
+				}
+			}
+			Event::MouseUp(_) => {
+				ctx.set_active(false);
+				_ui.drag_from = None;
+			}
             _ => ()
 		}
     }
@@ -84,7 +132,7 @@ impl Widget<UI> for dc::DomainColoring {
 
 fn ui_builder() -> impl Widget<UI> {
 
-	let mut dc = dc::DomainColoring::new(DC_SIZE, DC_SIZE, PREDEF_FUNCS[START_PREDEF_FUNC as usize]);
+	let mut dc = dc::DomainColoring::new(DC_SIZE, DC_SIZE, PREDEF_FUNCS[START_PREDEF_FUNC as usize]).expect("predefined function should compile");
 	dc.generate_parallel();
 
 	Flex::column()
@@ -94,23 +142,60 @@ fn ui_builder() -> impl Widget<UI> {
 					Stepper::new().with_range(0.0, 18.0).with_step(1.0).with_wraparound(true).padding((5.,5.)), UI::stepper_value)
 						.on_click(|_ctx, ui: &mut UI, _env| { 
 							ui.expression = PREDEF_FUNCS[ui.stepper_value as usize].to_string();
-							ui.dc.compile(&ui.expression);						
+							ui.compile();
 						}))
 				.with_child(Button::new("draw").on_click(|_ctx, ui: &mut UI, _env| { 
-					ui.dc.compile(&ui.expression);		
+					ui.compile();
+				}))
+				.with_child(Button::new("scheme").on_click(|_ctx, ui: &mut UI, _env| { 
+					let scheme = ui.dc.scheme.next();
+					ui.dc.set_scheme(scheme);
 					ui.update =! ui.update; // trigger update
-					ui.status = format!("compiled expression: {}", if ui.dc.zvm.ok() {"ok"} else {"error"} );
+					ui.status = format!("color scheme: {}", scheme.name());
+				}))
+				.with_child(Button::new("newton").on_click(|_ctx, ui: &mut UI, _env| { 
+					let mode = if ui.dc.mode == dc::RenderMode::Newton { dc::RenderMode::Domain } else { dc::RenderMode::Newton };
+					ui.dc.set_mode(mode);
+					ui.update =! ui.update; // trigger update
+					ui.status = format!("render mode: {:?}", mode);
+				}))
+				.with_child(Button::new("sphere").on_click(|_ctx, ui: &mut UI, _env| { 
+					let mode = if ui.dc.mode == dc::RenderMode::Riemann { dc::RenderMode::Domain } else { dc::RenderMode::Riemann };
+					ui.dc.set_mode(mode);
+					ui.update =! ui.update; // trigger update
+					ui.status = format!("render mode: {:?}", mode);
+				}))
+				.with_child(Button::new("warp").on_click(|_ctx, ui: &mut UI, _env| { 
+					let mode = if ui.dc.mode == dc::RenderMode::Warp { dc::RenderMode::Domain } else { dc::RenderMode::Warp };
+					ui.dc.set_mode(mode);
+					ui.update =! ui.update; // trigger update
+					ui.status = format!("render mode: {:?}", mode);
+				}))
+				.with_child(Button::new("f64").on_click(|_ctx, ui: &mut UI, _env| { 
+					let precision = if ui.dc.zvm.precision() == dc::Precision::F64 { dc::Precision::F32 } else { dc::Precision::F64 };
+					ui.dc.set_precision(precision);
+					ui.update =! ui.update; // trigger update
+					ui.status = format!("precision: {:?}", precision);
+				}))
+				.with_child(Button::new("markers").on_click(|_ctx, ui: &mut UI, _env| { 
+					ui.dc.set_markers(!ui.dc.markers);
+					ui.update =! ui.update; // trigger update
+					ui.status = if ui.dc.markers { format!("{} zeros & poles", ui.dc.zeros_poles().len()) } else { "markers off".to_string() };
 				}))
 				.with_child(Align::left(TextBox::new().fix_width(800.).lens(UI::expression))))
+		.with_child(Align::left(Label::new(|ui: &UI, _: &Env| { ui.error.clone() } ).with_text_color(Color::rgb8(0xff, 0x40, 0x40)).with_text_size(10.0)))
 				
 		.with_child(dc)
 		.with_child(Align::left(Label::new(|ui: &UI, _: &Env| { ui.status.clone() } ).with_text_size(10.0)))
 }
 
 fn main() {
+	let args : Vec<String> = std::env::args().skip(1).collect();
+	if let Some(code) = cli::run(&args) { std::process::exit(code) } // headless modes
+
 	let main_window = WindowDesc::new(ui_builder)
 	.title("Domain Coloring")
-	.window_size((WINDOW_SIZE.0, WINDOW_SIZE.1+40.));
+	.window_size((WINDOW_SIZE.0, WINDOW_SIZE.1+55.));
 
 AppLauncher::with_window(main_window)
 	.launch(UI::new(DC_SIZE, DC_SIZE, PREDEF_FUNCS[START_PREDEF_FUNC as usize]))
@@ -0,0 +1,123 @@
+/*
+	Riemann sphere: domain coloring through inverse stereographic projection
+	north pole (0,0,1) is z = infinity, south pole (0,0,-1) is z = 0
+*/
+
+use super::*;
+use std::io::{self, Write, BufWriter};
+use std::fs::File;
+use std::path::Path;
+
+const POLE_EPS : f32 = 1e-6; // 1 - z3 below this is the point at infinity
+
+pub fn z_to_sphere(z : Cf32) -> [f32; 3] {
+	let n = z.norm_sqr();
+	[2. * z.re / (n + 1.), 2. * z.im / (n + 1.), (n - 1.) / (n + 1.)]
+}
+
+pub fn sphere_to_z(p : [f32; 3]) -> Cf32 { // projection from the north pole onto the equatorial plane
+	if 1. - p[2] < POLE_EPS { 
+		Cf32::new(1. / POLE_EPS, 0.) 
+	} else if p[2] > 0. { // (x+iy)/(1-z) == (1+z)/(x-iy), stable near infinity
+		(1. + p[2]) / Cf32::new(p[0], -p[1])
+	} else {
+		Cf32::new(p[0], p[1]) / (1. - p[2])
+	}
+}
+
+pub fn lon_lat_to_sphere(lon : f32, lat : f32) -> [f32; 3] {
+	[lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+// uv sphere, seam vertices duplicated for texture coordinates, no degenerate pole triangles
+#[derive(Clone, Debug, Default)]
+pub struct SphereMesh {
+	pub vertices : Vec<[f32; 3]>,
+	pub uvs 	 : Vec<[f32; 2]>,
+	pub colors 	 : Vec<[u8; 3]>,
+	pub faces 	 : Vec<[u32; 3]>,
+}
+
+impl SphereMesh {
+	pub fn write_obj(&self, name : &str) -> io::Result<()> { // vertex colors as 'v x y z r g b'
+		let mut f = BufWriter::new(File::create(name)?);
+		writeln!(f, "# riemann sphere, {} vertices, {} faces", self.vertices.len(), self.faces.len())?;
+		for (v, c) in self.vertices.iter().zip(self.colors.iter()) {
+			writeln!(f, "v {} {} {} {:.4} {:.4} {:.4}", v[0], v[1], v[2], c[0] as f32 / 255., c[1] as f32 / 255., c[2] as f32 / 255.)?;
+		}
+		for uv in &self.uvs {
+			writeln!(f, "vt {} {}", uv[0], uv[1])?;
+		}
+		for t in &self.faces {
+			writeln!(f, "f {0}/{0} {1}/{1} {2}/{2}", t[0] + 1, t[1] + 1, t[2] + 1)?;
+		}
+		Ok(())
+	}
+
+	pub fn write_ply(&self, name : &str) -> io::Result<()> { // ascii ply
+		let mut f = BufWriter::new(File::create(name)?);
+		writeln!(f, "ply\nformat ascii 1.0\nelement vertex {}", self.vertices.len())?;
+		writeln!(f, "property float x\nproperty float y\nproperty float z\nproperty uchar red\nproperty uchar green\nproperty uchar blue")?;
+		writeln!(f, "element face {}\nproperty list uchar int vertex_indices\nend_header", self.faces.len())?;
+		for (v, c) in self.vertices.iter().zip(self.colors.iter()) {
+			writeln!(f, "{} {} {} {} {} {}", v[0], v[1], v[2], c[0], c[1], c[2])?;
+		}
+		for t in &self.faces {
+			writeln!(f, "3 {} {} {}", t[0], t[1], t[2])?;
+		}
+		Ok(())
+	}
+
+	pub fn write(&self, name : &str) -> io::Result<()> { // format by extension: .obj | .ply
+		match Path::new(name).extension().and_then(|e| e.to_str()) {
+			Some("obj") => self.write_obj(name),
+			Some("ply") => self.write_ply(name),
+			_ 			=> Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{}: expected .obj or .ply", name))),
+		}
+	}
+}
+
+impl DomainColoring {
+	// equirectangular pixel -> sphere, x: longitude -pi..pi, y: latitude pi/2 (infinity) .. -pi/2 (0)
+	pub fn equirect_to_sphere(x : f32, y : f32, w : usize, h : usize) -> [f32; 3] {
+		lon_lat_to_sphere((x + 0.5) / w as f32 * PI2 - PI, PI / 2. - (y + 0.5) / h as f32 * PI)
+	}
+
+	pub(crate) fn generate_riemann(&mut self) {
+		let (w, h, zf, scheme, size) = (self.w as usize, self.h as usize, ZFunc::new(&self.zvm), self.scheme, (self.w * self.h) as usize);
+
+		self.image = (0..size).into_par_iter().map(|index| {
+			let p = Self::equirect_to_sphere((index % w) as f32, (index / w) as f32, w, h);
+			let z = sphere_to_z(p);
+			Self::color(scheme, zf.eval(Cf64::new(z.re as f64, z.im as f64)))
+		}).collect()
+	}
+
+	pub fn riemann_sphere(&self, slices : usize, stacks : usize) -> SphereMesh {
+		let (zf, scheme) = (ZFunc::new(&self.zvm), self.scheme);
+		let mut mesh = SphereMesh::default();
+
+		for i in 0..=stacks {
+			let lat = PI / 2. - i as f32 / stacks as f32 * PI;
+			for j in 0..=slices {
+				let lon = j as f32 / slices as f32 * PI2 - PI;
+				let p = lon_lat_to_sphere(lon, lat);
+				let z = sphere_to_z(p);
+				let px = Self::color(scheme, zf.eval(Cf64::new(z.re as f64, z.im as f64)));
+				mesh.vertices.push(p);
+				mesh.uvs.push([j as f32 / slices as f32, 1. - i as f32 / stacks as f32]);
+				mesh.colors.push((px.to_be_bytes()[0..3]).try_into().expect("image should have u32 type!"));
+			}
+		}
+
+		let row = (slices + 1) as u32;
+		for i in 0..stacks as u32 {
+			for j in 0..slices as u32 {
+				let (a, b) = (i * row + j, (i + 1) * row + j);
+				if i != 0 					{ mesh.faces.push([a, b, a + 1]) }
+				if i != stacks as u32 - 1 	{ mesh.faces.push([a + 1, b, b + 1]) }
+			}
+		}
+		mesh
+	}
+}
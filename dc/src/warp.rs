@@ -0,0 +1,130 @@
+/*
+	conformal warping: each output pixel z samples a source texture at f(z)
+*/
+
+use super::*;
+use std::sync::Arc;
+
+// texel addressing outside the texture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EdgeMode {
+	Wrap, 	// tile
+	Clamp, 	// repeat border texels
+	Mirror, // tile with every other copy flipped
+}
+
+impl Default for EdgeMode { fn default() -> Self { EdgeMode::Wrap } }
+
+impl EdgeMode {
+	pub const ALL : [EdgeMode; 3] = [EdgeMode::Wrap, EdgeMode::Clamp, EdgeMode::Mirror];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			EdgeMode::Wrap 	 => "wrap",
+			EdgeMode::Clamp  => "clamp",
+			EdgeMode::Mirror => "mirror",
+		}
+	}
+
+	pub fn from_name(name : &str) -> Option<Self> {
+		Self::ALL.iter().find(|e| e.name() == name).copied()
+	}
+
+	fn index(&self, i : i64, n : usize) -> usize {
+		let n = n as i64;
+		(match self {
+			EdgeMode::Wrap 	 => i.rem_euclid(n),
+			EdgeMode::Clamp  => i.max(0).min(n - 1),
+			EdgeMode::Mirror => { let m = i.rem_euclid(2 * n); if m < n { m } else { 2 * n - 1 - m } }
+		}) as usize
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Texture {
+	pub w 	   : usize,
+	pub h 	   : usize,
+	pub pixels : Vec<[u8; 3]>,
+}
+
+impl Texture {
+	pub fn load(name : &str) -> image::ImageResult<Self> {
+		let img = image::open(name)?.to_rgb8();
+		let (w, h) = (img.width() as usize, img.height() as usize);
+		Ok(Self { w, h, pixels : img.pixels().map(|p| p.0).collect() })
+	}
+
+	pub fn checkerboard(size : usize, cells : usize) -> Self {
+		let cell = (size / cells.max(1)).max(1);
+		Self { w : size, h : size, pixels : (0..size * size).map(|i|
+			if (i % size / cell + i / size / cell) % 2 == 0 { [0xf0, 0xf0, 0xf0] } else { [0x30, 0x30, 0x60] }
+		).collect() }
+	}
+
+	fn texel(&self, x : i64, y : i64, edge : EdgeMode) -> [f32; 3] {
+		let p = self.pixels[edge.index(y, self.h) * self.w + edge.index(x, self.w)];
+		[p[0] as f32, p[1] as f32, p[2] as f32]
+	}
+
+	// bilinear sample at pixel coords, texel centers at integers
+	pub fn sample(&self, x : f32, y : f32, edge : EdgeMode) -> [u8; 3] {
+		let (x0, y0) = (x.floor(), y.floor());
+		let (fx, fy) = (x - x0, y - y0);
+		let (x0, y0) = (x0 as i64, y0 as i64);
+		let (a, b, c, d) = (self.texel(x0, y0, edge), self.texel(x0 + 1, y0, edge), self.texel(x0, y0 + 1, edge), self.texel(x0 + 1, y0 + 1, edge));
+		let mut rgb = [0_u8; 3];
+		for i in 0..3 {
+			let top = a[i] + (b[i] - a[i]) * fx;
+			let bottom = c[i] + (d[i] - c[i]) * fx;
+			rgb[i] = (top + (bottom - top) * fy).round() as u8;
+		}
+		rgb
+	}
+}
+
+// texture placed on the complex plane by source viewport
+#[derive(Clone, Debug)]
+pub struct Warp {
+	pub texture : Arc<Texture>,
+	pub source  : Viewport,
+	pub edge 	: EdgeMode,
+}
+
+impl Default for Warp {
+	fn default() -> Self { Warp::new(Texture::checkerboard(512, 16)) }
+}
+
+impl Warp {
+	pub fn new(texture : Texture) -> Self {
+		Self { texture : Arc::new(texture), source : Viewport::new(Cf64::new(0., 0.), 2.), edge : EdgeMode::default() }
+	}
+
+	pub fn with_source(mut self, source : Viewport) -> Self {
+		self.source = source;
+		self
+	}
+
+	pub fn with_edge(mut self, edge : EdgeMode) -> Self {
+		self.edge = edge;
+		self
+	}
+
+	pub fn sample(&self, z : Cf32) -> Option<[u8; 3]> { // None: f(z) not finite
+		if !z.is_finite() { return None }
+		let (x, y) = self.source.z_to_pixel(Cf64::new(z.re as f64, z.im as f64), self.texture.w, self.texture.h);
+		if !x.is_finite() || !y.is_finite() || x.abs() > 1e9 || y.abs() > 1e9 { return None }
+		Some(self.texture.sample(x, y, self.edge))
+	}
+}
+
+impl DomainColoring {
+	pub(crate) fn generate_warp(&mut self) {
+		let warp : &Warp = self.warp.get_or_insert_with(Warp::default);
+		let (w, h, zf, vp, size) = (self.w as usize, self.h as usize, ZFunc::new(&self.zvm), self.viewport, (self.w * self.h) as usize);
+
+		self.image = (0..size).into_par_iter().map(|index| {
+			let z = vp.pixel_to_z64((index % w) as f64, (index / w) as f64, w, h);
+			Self::rgb_2_u32(warp.sample(zf.eval(z)).unwrap_or([0, 0, 0]))
+		}).collect()
+	}
+}
@@ -4,8 +4,10 @@
 
 #![allow(dead_code)]
 
-use std::f32::consts::{PI, E};
+use std::f64::consts::{PI, E};
+use std::fmt;
 use num::complex::Complex as complex;
+use num::{Float, traits::FloatConst};
 
 use druid::{
     AppDelegate, AppLauncher, Command, Data, DelegateCtx, Env, ExtEventSink, Lens, LocalizedString,
@@ -23,57 +25,271 @@ enum Symbols {
     FSIN = 90, FCOS = 91, FTAN = 92, FEXP = 93, FLOG = 94, FLOG10 = 95,
     FINT = 96, FSQRT = 97, FASIN = 98, FACOS = 99, FATAN = 100, FABS = 101,
     FC = 102, SPI = 103, SPHI = 104,
-    PUSHC = 112, PUSHZ = 113, PUSHI = 114, PUSHCC = 115,
-    NEG = 116,
+    FSINH = 105, FCOSH = 106, FTANH = 107, FASINH = 108, FACOSH = 109, FATANH = 110,
+    FRE = 111, FIM = 112, FARG = 113, FCONJ = 114, SE = 115, FGAMMA = 116, FZETA = 117,
+    FPOW = 118, FPOLAR = 119,
+    PUSHC = 130, PUSHZ = 131, PUSHI = 132, PUSHCC = 133,
+    NEG = 134, POWI = 135,
     
     END = 200
 }
 
-const FUNC_NAMES : [&'static str; 15] = ["sin", "cos", "tan", "exp", "log", "log10", "int", "sqrt", "asin",	"acos", "atan", "abs", "c", "pi", "phi"];
+const FUNC_NAMES : [&'static str; 30] = ["sin", "cos", "tan", "exp", "log", "log10", "int", "sqrt", "asin",	"acos", "atan", "abs", "c", "pi", "phi",
+	"sinh", "cosh", "tanh", "asinh", "acosh", "atanh", "re", "im", "arg", "conj", "e", "gamma", "zeta", "pow", "polar"];
 
-const PHI : f32 = 0.618033988_f32;
+const PHI : f64 = 0.618033988749895;
 
 pub type CF32 = complex<f32>;
+pub type CF64 = complex<f64>;
 pub type ZFN = fn(CF32) -> CF32;
 
+// float types the vm evaluates in, constants are kept as f64 in code
+pub trait ZFloat : Float + FloatConst + fmt::Debug + fmt::Display + Send + Sync + 'static {}
+impl ZFloat for f32 {}
+impl ZFloat for f64 {}
+
+// evaluation & constant folding precision
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+	F32,
+	F64,
+}
+
+impl Default for Precision { fn default() -> Self { Precision::F32 } }
+
+fn t<T : ZFloat>(x : f64) -> T { T::from(x).unwrap() }
+fn ct<T : ZFloat>(re : f64, im : f64) -> complex<T> { complex::new(t(re), t(im)) }
+fn to_cf64<T : ZFloat>(z : complex<T>) -> CF64 { CF64::new(z.re.to_f64().unwrap(), z.im.to_f64().unwrap()) }
+fn from_cf64<T : ZFloat>(z : CF64) -> complex<T> { ct(z.re, z.im) }
+
+// Lanczos approximation g=7, n=9, reflection for re(z) < 0.5
+fn gamma(z : CF64) -> CF64 {
+	const G : f64 = 7.;
+	const P : [f64; 9] = [0.99999999999980993, 676.5203681218851, -1259.1392167224028, 771.32342877765313, -176.61502916214059, 
+		12.507343278686905, -0.13857109526572012, 9.9843695780195716e-6, 1.5056327351493116e-7];
+	let pi = std::f64::consts::PI;
+
+	if z.re < 0.5 { pi / ((pi * z).sin() * gamma(1. - z)) }
+	else {
+		let z = z - 1.;
+		let x = (1..P.len()).fold(CF64::new(P[0], 0.), |x, i| x + P[i] / (z + i as f64));
+		let t = z + G + 0.5;
+		(2. * pi).sqrt() * t.powc(z + 0.5) * (-t).exp() * x
+	}
+}
+
+// Euler-Maclaurin summation N=10, 7 Bernoulli terms, functional equation for re(s) < 0
+fn zeta(s : CF64) -> CF64 {
+	const N : f64 = 10.;
+	const B2K_FACT : [f64; 7] = [1./12., -1./720., 1./30240., -1./1209600., 1./47900160., -691./1307674368000., 1./74724249600.]; // B(2k)/(2k)!
+	let pi = std::f64::consts::PI;
+
+	if s.re < 0. { 
+		return CF64::new(2., 0.).powc(s) * CF64::new(pi, 0.).powc(s - 1.) * (pi * s / 2.).sin() * gamma(1. - s) * zeta(1. - s) 
+	}
+
+	let n_pow = |e : CF64| (e * N.ln()).exp(); // N^e
+	let mut sum = (1..N as usize).fold(CF64::new(0., 0.), |sum, n| sum + (-s * (n as f64).ln()).exp());
+	sum += n_pow(1. - s) / (s - 1.) + n_pow(-s) / 2.;
+
+	let (mut prod, mut np) = (s, n_pow(-s - 1.)); // s(s+1)..(s+2k-2), N^(-s-2k+1)
+	for (k, b) in B2K_FACT.iter().enumerate() {
+		sum += *b * prod * np;
+		let k2 = (2 * k + 1) as f64;
+		prod *= (s + k2) * (s + k2 + 1.);
+		np /= N * N;
+	}
+	sum
+}
+
+// compile error: first offending token
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZError {
+	pub pos		 :usize,  // char offset in source
+	pub expected :String,
+	pub found	 :String,
+}
+
+impl fmt::Display for ZError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "syntax error at {}: expected {}, found {}", self.pos, self.expected, 
+			if self.found.is_empty() { "end of expression".to_string() } else { format!("'{}'", self.found) })
+	}
+}
+
+impl std::error::Error for ZError {}
+
+// dual number f + f' eps, eps^2 = 0, forward mode differentiation
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Dual<T> {
+	v : complex<T>,
+	d : complex<T>,
+}
+
+impl<T : ZFloat> Dual<T> {
+	fn new(v : complex<T>, d : complex<T>) -> Self { Self { v, d } }
+	fn cons(v : complex<T>) -> Self { Self { v, d : ct(0., 0.) } }
+	fn chain(self, fv : complex<T>, dfv : complex<T>) -> Self { Self { v : fv, d : dfv * self.d } } // f(self), f'(self)
+
+	fn add(self, o : Self) -> Self { Self::new(self.v + o.v, self.d + o.d) }
+	fn sub(self, o : Self) -> Self { Self::new(self.v - o.v, self.d - o.d) }
+	fn mul(self, o : Self) -> Self { Self::new(self.v * o.v, self.d * o.v + self.v * o.d) }
+	fn div(self, o : Self) -> Self { Self::new(self.v / o.v, (self.d * o.v - self.v * o.d) / (o.v * o.v)) }
+	fn pow(self, o : Self) -> Self {
+		match int_exponent(o.v) {
+			Some(n) if o.d == ct(0., 0.) => 
+				self.chain(self.v.powi(n), if n == 0 { ct(0., 0.) } else { self.v.powi(n - 1) * t::<T>(n as f64) }),
+			_ => { // a^b = exp(b log a)
+				let p = self.v.powc(o.v);
+				Self::new(p, p * (o.d * self.v.ln() + o.v * self.d / self.v))
+			}
+		}
+	}
+}
+
+fn diff64<T : ZFloat>(f : fn(CF64) -> CF64, z : complex<T>) -> complex<T> { // central difference
+	let z = to_cf64(z);
+	let h = 1e-6 * (1. + z.norm());
+	from_cf64((f(z + h) - f(z - h)) / (2. * h))
+}
+
+fn eval64<T : ZFloat>(f : fn(CF64) -> CF64, z : complex<T>) -> complex<T> { from_cf64(f(to_cf64(z))) } // gamma, zeta
+
+// decoded instruction
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Ins {
+	Const(CF64),
+	Z,
+	PowI(i32),
+	Op(Symbols),
+}
+
+impl Symbols {
+	fn arity(&self) -> usize {
+		match self {
+			Symbols::PLUS | Symbols::MINUS | Symbols::MULT | Symbols::DIV | Symbols::POWER |
+			Symbols::FC | Symbols::FPOW | Symbols::FPOLAR => 2,
+			_ => 1,
+		}
+	}
+}
+
+fn int_exponent<T : ZFloat>(e : complex<T>) -> Option<i32> { // exponents evaluated with powi
+	if e.im == T::zero() && e.re.fract() == T::zero() && e.re.abs() < t(1e6) { e.re.to_i32() } else { None }
+}
+
+type ZNode<T> = Box<dyn Fn(complex<T>) -> complex<T> + Send + Sync>;
+
+// code compiled to a tree of closures, same results as ZVm::eval_t
+pub struct ZClosure<T = f32> {
+	f : ZNode<T>,
+}
+
+impl<T : ZFloat> ZClosure<T> {
+	pub fn eval(&self, z : complex<T>) -> complex<T> { (self.f)(z) }
+}
+
+// closure tree node, leafs are kept apart to specialize their parents
+enum Node<T> {
+	Const(complex<T>),
+	Z,
+	F(ZNode<T>),
+}
+
+impl<T : ZFloat> Node<T> {
+	fn unary<OP>(self, op : OP) -> Self where OP : Fn(complex<T>) -> complex<T> + Send + Sync + 'static {
+		match self {
+			Node::Const(c) => Node::Const(op(c)),
+			Node::Z 	   => Node::F(Box::new(op)),
+			Node::F(f) 	   => Node::F(Box::new(move |z| op(f(z)))),
+		}
+	}
+
+	fn binary<OP>(self, b : Self, op : OP) -> Self where OP : Fn(complex<T>, complex<T>) -> complex<T> + Send + Sync + 'static {
+		match (self, b) {
+			(Node::Const(a), Node::Const(b)) => Node::Const(op(a, b)),
+			(Node::Const(a), Node::Z) 		 => Node::F(Box::new(move |z| op(a, z))),
+			(Node::Z, Node::Const(b)) 		 => Node::F(Box::new(move |z| op(z, b))),
+			(Node::Z, Node::Z) 				 => Node::F(Box::new(move |z| op(z, z))),
+			(Node::Const(a), Node::F(g)) 	 => Node::F(Box::new(move |z| op(a, g(z)))),
+			(Node::F(f), Node::Const(b)) 	 => Node::F(Box::new(move |z| op(f(z), b))),
+			(Node::Z, Node::F(g)) 			 => Node::F(Box::new(move |z| op(z, g(z)))),
+			(Node::F(f), Node::Z) 			 => Node::F(Box::new(move |z| op(f(z), z))),
+			(Node::F(f), Node::F(g)) 		 => Node::F(Box::new(move |z| op(f(z), g(z)))),
+		}
+	}
+
+	fn into_closure(self) -> ZNode<T> {
+		match self {
+			Node::Const(c) => Box::new(move |_| c),
+			Node::Z 	   => Box::new(|z| z),
+			Node::F(f) 	   => f,
+		}
+	}
+}
+
 #[derive(Clone, Debug, Data)]
 pub struct ZVm {
 	pub source	:String,
 	#[data(ignore)]ch		:char,
 	#[data(ignore)]ixs		:usize,
 	#[data(ignore)]sym		:Symbols,
+	#[data(ignore)]sym_pos	:usize,
 	#[data(ignore)]ident	:String,
-	#[data(ignore)]nval		:f32,
-	#[data(ignore)]err 		:bool,
+	#[data(ignore)]nval		:f64,
+	#[data(ignore)]err 		:Option<ZError>,
 	#[data(ignore)]code		:Vec<u32>,
+	#[data(ignore)]precision:Precision,
+	#[data(ignore)]depth	:usize, // stack size of the code
 }
-impl Default for ZVm { fn default() -> Self { ZVm::new("") } }
+impl Default for ZVm { fn default() -> Self { ZVm::empty("") } } // evals to 0
 
 
 impl ZVm {
-	pub fn new(source : &str) -> Self {
-		let mut s=Self {
+	fn empty(source : &str) -> Self {
+		Self {
 			source	:String::from(source), 
 			ch		:' ', 
 			ixs		:0, 
 			sym		:Symbols::SNULL, 
+			sym_pos	:0,
 			ident	:"".to_string(), 
-			nval	:0_f32, 
-			err		:false, 
-			code	:vec![], 
-		};
-		s.compile();
-		s
+			nval	:0., 
+			err		:None, 
+			code	:vec![Symbols::END as u32], 
+			precision:Precision::F32,
+			depth	:0,
+		}
+	}
+
+	pub fn new(source : &str) -> Result<Self, ZError> {
+		Self::with_precision(source, Precision::F32)
+	}
+
+	pub fn with_precision(source : &str, precision : Precision) -> Result<Self, ZError> { // constants folded in precision
+		let mut s = Self::empty(source);
+		s.precision = precision;
+		s.compile()?;
+		s.optimize();
+		Ok(s)
 	}
 
-	fn u32_to_f32(u : u32) -> f32 {	f32::from_ne_bytes(u.to_ne_bytes()) }
-	fn f32_to_u32(f : f32) -> u32 {	u32::from_ne_bytes(f.to_ne_bytes()) }
+	pub fn precision(&self) -> Precision { self.precision }
+
+	fn push_f64(&mut self, f : f64) { let b = f.to_bits(); self.code.push(b as u32); self.code.push((b >> 32) as u32) } // 2 words
+	fn code_f64(&self, pc : usize) -> f64 { f64::from_bits(self.code[pc] as u64 | (self.code[pc + 1] as u64) << 32) }
+	fn code_const(&self, pc : usize) -> (CF64, usize) { // PUSHC / PUSHCC at pc -> constant, words used
+		match Self::u32_2_sym(self.code[pc]) {
+			Symbols::PUSHC => (CF64::new(self.code_f64(pc + 1), 0.), 2),
+			_ 			   => (CF64::new(self.code_f64(pc + 1), self.code_f64(pc + 3)), 4),
+		}
+	}
 	fn u32_2_sym(c : u32) -> Symbols { unsafe { ::std::mem::transmute(c as u8) } }
 	fn usize_2_sym(c : usize) -> Symbols { unsafe { ::std::mem::transmute(c as u8) } }
 
 	fn getch(&mut self) -> char {
 		self.ch='\0';
-		if self.ixs < self.source.len() {
+		if self.ixs < self.source.chars().count() {
 			self.ch = self.source.chars().nth(self.ixs).unwrap();
 			self.ixs+=1;
 		} 
@@ -83,10 +299,11 @@ impl ZVm {
 	fn getsym(&mut self) -> Symbols {
 		self.sym = Symbols::SNULL;
 		self.ident.clear();
-		self.nval = 0_f32;
+		self.nval = 0.;
 
 		// skip whites
 		while self.ch != '\0' && self.ch <= ' ' { let _ = self.getch(); }
+		self.sym_pos = if self.ch == '\0' { self.source.chars().count() } else { self.ixs - 1 };
 
 		// scan symbol
 		if self.ch.is_alphabetic() { // ident
@@ -102,16 +319,23 @@ impl ZVm {
 				if index.is_some() { // sym = FSIN + index
 					self.sym = unsafe { ::std::mem::transmute(Symbols::FSIN as u8 + index.unwrap() as u8) }
 				} else { // error
-					self.sym = Symbols::SNULL
+					self.sym = Symbols::SNULL;
+					self.error("function, constant, z or i");
 				}				
 			}
 		} else if self.ch.is_digit(10) { // number
 			while self.ch.is_digit(10) || self.ch=='.' || self.ch=='e' || self.ch=='E' {
 				self.ident.push(self.ch);
 				self.getch();
+				if (self.ch=='+' || self.ch=='-') && self.ident.ends_with(['e', 'E']) { // 1e-3
+					self.ident.push(self.ch);
+					self.getch();
+				}
+			}
+			match self.ident.parse::<f64>() { // atof
+				Ok(nval) => { self.sym = Symbols::NUMBER; self.nval = nval }
+				Err(_)   => self.error("number"),
 			}
-			self.sym = Symbols::NUMBER;
-			self.nval = self.ident.parse::<f32>().unwrap(); // atof
 		} else {
 			self.sym = 
 				match self.ch {
@@ -126,23 +350,40 @@ impl ZVm {
 					',' => Symbols::COMMA,
 					_   => Symbols::SNULL,				
 				};
+			if self.ch != '\0' { self.ident.push(self.ch) }
+			if self.sym == Symbols::SNULL && self.ch != '\0' { self.error("operator or operand") }
 			self.getch();
 		}
 		self.sym
 	}
 
+	fn error(&mut self, expected : &str) { // keep first error only
+		if self.err.is_none() {
+			self.err = Some(ZError { pos : self.sym_pos, expected : expected.to_string(), found : self.ident.clone() })
+		}
+	}
+
+	fn sym_name(sym : Symbols) -> &'static str {
+		match sym {
+			Symbols::OPAREN => "'('",
+			Symbols::CPAREN => "')'",
+			Symbols::COMMA  => "','",
+			_ => "operand",
+		}
+	}
+
 	fn getsym_check(&mut self, chk_sym : Symbols) -> Symbols {
-		if self.getsym() != chk_sym { self.err = true; self.sym = Symbols::SNULL }
+		if self.getsym() != chk_sym { self.error(Self::sym_name(chk_sym)); self.sym = Symbols::SNULL }
 		self.sym
 	}
 	fn sym_check(&mut self, chk_sym : Symbols) -> Symbols {
-		if self.sym != chk_sym { self.err = true; self.sym = Symbols::SNULL }
+		if self.sym != chk_sym { self.error(Self::sym_name(chk_sym)); self.sym = Symbols::SNULL }
 		else { self.getsym(); }
 		self.sym
 	}
 
 	fn getsym_not_null(&mut self) -> Symbols {
-		if self.getsym() == Symbols::SNULL { self.err = true }
+		if self.getsym() == Symbols::SNULL { self.error("operand") }
 		self.sym
 	}
 
@@ -151,7 +392,7 @@ impl ZVm {
 		match sym {
 			Symbols::PUSHC => {
 				self.code.push(sym as u32);
-				self.code.push(Self::f32_to_u32(self.nval)); // self.nval
+				self.push_f64(self.nval);
 			}
 			_ => {	
 				self.code.push(sym as u32) ;
@@ -162,7 +403,7 @@ impl ZVm {
 	fn c_e3(&mut self) {
 		
 
-		if !self.err {
+		if self.err.is_none() {
 			
 			match self.sym {
 				Symbols::OPAREN => {
@@ -193,20 +434,24 @@ impl ZVm {
 				}
 				Symbols::FSIN  | Symbols::FCOS | Symbols::FTAN |  Symbols::FASIN |
                 Symbols::FACOS | Symbols::FATAN| Symbols::FEXP |  Symbols::FINT  |
-                Symbols::FABS  | Symbols::FLOG | Symbols::FLOG10| Symbols::FSQRT => {
+                Symbols::FABS  | Symbols::FLOG | Symbols::FLOG10| Symbols::FSQRT |
+                Symbols::FSINH | Symbols::FCOSH| Symbols::FTANH |  Symbols::FASINH|
+                Symbols::FACOSH| Symbols::FATANH| Symbols::FRE  |  Symbols::FIM   |
+                Symbols::FARG  | Symbols::FCONJ| Symbols::FGAMMA|  Symbols::FZETA => {
 					let tsym = self.sym;
                     self.getsym_check(Symbols::OPAREN);
                     self.c_e3();
                     self.gen(tsym);
 				}
-				Symbols::FC => {
+				Symbols::FC | Symbols::FPOW | Symbols::FPOLAR => { // f(e0, e0)
+					let tsym = self.sym;
 					self.getsym_check(Symbols::OPAREN);
 					self.getsym();
-                    self.c_e3();
+                    self.c_e0();
                     self.sym_check(Symbols::COMMA);
-                    self.c_e3();
+                    self.c_e0();
                     self.sym_check(Symbols::CPAREN);
-                    self.gen(Symbols::FC);
+                    self.gen(tsym);
 				},
 				Symbols::SPI => {
                     self.getsym();
@@ -216,11 +461,13 @@ impl ZVm {
                     self.getsym();
                     self.nval=PHI; self.gen(Symbols::PUSHC);
 				},
+                Symbols::SE => {
+                    self.getsym();
+                    self.nval=E; self.gen(Symbols::PUSHC);
+				},
 
-				Symbols::SNULL => {},
-					
 				_ => { 
-					self.err=true;					
+					self.error("operand");
 				}
 			}
 		}
@@ -228,7 +475,7 @@ impl ZVm {
 
 	fn c_e2(&mut self) {
 		
-		if !self.err {
+		if self.err.is_none() {
 			
 			self.c_e3();
 
@@ -246,7 +493,7 @@ impl ZVm {
 	}
 
 	fn c_e1(&mut self) {
-		if !self.err {
+		if self.err.is_none() {
 
 			self.c_e2();
 			
@@ -270,7 +517,7 @@ impl ZVm {
 
 	fn c_e0(&mut self) {
 		
-		if !self.err {
+		if self.err.is_none() {
 			
 			self.c_e1();
 			
@@ -292,29 +539,42 @@ impl ZVm {
 		}
 	}
 
-	pub fn compile(&mut self) {
-		self.err = false;
+	pub fn compile(&mut self) -> Result<(), ZError> {
+		self.err = None;
+		self.code.clear();
 
 		self.getsym();
 		self.c_e0();
+		if !self.ident.is_empty() { self.error("operator or end of expression") } // trailing tokens
 
-		if self.err { self.code.clear() }
+		if self.err.is_some() { self.code.clear() }
 		self.gen(Symbols::END);
+		self.depth = self.stack_depth();
+
+		match &self.err {
+			Some(err) => Err(err.clone()),
+			None	  => Ok(()),
+		}
 	}
 	
-	pub fn eval(&self, z: CF32) -> CF32 {
+	pub fn eval(&self, z : CF32) -> CF32 { self.eval_t(z) }
+	pub fn eval64(&self, z : CF64) -> CF64 { self.eval_t(z) }
+
+	pub fn eval_t<T : ZFloat>(&self, z : complex<T>) -> complex<T> {
 		
-		if self.err { return CF32::new(0., 0.) }
+		let zero = ct::<T>(0., 0.);
+		if self.err.is_some() { return zero }
 
 		let mut pc : usize = 0;
 		let mut sp : usize = 0;
-		let mut stack : Vec<CF32> = vec![CF32::new(0.,0.); 16];
+		let mut stack : Vec<complex<T>> = vec![zero; self.depth];
 
 		loop {
 			match Self::u32_2_sym(self.code[pc]) {
-				Symbols::PUSHC => {
-					pc+=1;
-					stack[sp] = CF32::new(Self::u32_to_f32( self.code[pc] ), 0.);
+				Symbols::PUSHC | Symbols::PUSHCC => {
+					let (c, words) = self.code_const(pc);
+					stack[sp] = from_cf64(c);
+					pc+=words;
 					sp+=1
 				}
 				Symbols::PUSHZ => {
@@ -322,20 +582,23 @@ impl ZVm {
 					sp+=1
 				}
 				Symbols::PUSHI => {
-					stack[sp] = CF32::new(0., 1.);
+					stack[sp] = ct(0., 1.);
 					sp+=1
 				}
-				Symbols::PLUS  => {	sp-=1;	let zz = stack[sp];	stack[sp - 1] += zz;	}
-				Symbols::MINUS => {	sp-=1;	let zz = stack[sp];	stack[sp - 1] -= zz;	}
-				Symbols::MULT  => {	sp-=1;	let zz = stack[sp];	stack[sp - 1] *= zz;	}
-				Symbols::DIV   => {	sp-=1;	let zz = stack[sp];	stack[sp - 1] /= zz;	}
-				Symbols::POWER => {	
+				Symbols::PLUS  => {	sp-=1;	let zz = stack[sp];	stack[sp - 1] = stack[sp - 1] + zz;	}
+				Symbols::MINUS => {	sp-=1;	let zz = stack[sp];	stack[sp - 1] = stack[sp - 1] - zz;	}
+				Symbols::MULT  => {	sp-=1;	let zz = stack[sp];	stack[sp - 1] = stack[sp - 1] * zz;	}
+				Symbols::DIV   => {	sp-=1;	let zz = stack[sp];	stack[sp - 1] = stack[sp - 1] / zz;	}
+				Symbols::POWER | Symbols::FPOW => {	
 					sp-=1;	
 					let zz = stack[sp]; 
-					let zz = stack[sp-1].powc(zz);	
-					stack[sp - 1] += zz;	
+					stack[sp - 1] = match int_exponent(zz) {
+						Some(n) => stack[sp - 1].powi(n),
+						None 	=> stack[sp - 1].powc(zz),
+					};
 				}
-				Symbols::NEG   => {	sp-=1;	let zz = stack[sp - 1];	stack[sp - 1] = -zz; }
+				Symbols::POWI => { pc+=1; stack[sp - 1] = stack[sp - 1].powi(self.code[pc] as i32) }
+				Symbols::NEG   => {	let zz = stack[sp - 1];	stack[sp - 1] = -zz; }
 
 				Symbols::FSIN   => { stack[sp - 1] = stack[sp - 1].sin()	}
 				Symbols::FCOS   => { stack[sp - 1] = stack[sp - 1].cos()	}
@@ -344,23 +607,275 @@ impl ZVm {
 				Symbols::FACOS  => { stack[sp - 1] = stack[sp - 1].acos()	}
 				Symbols::FATAN  => { stack[sp - 1] = stack[sp - 1].atan()	}
 				Symbols::FEXP   => { stack[sp - 1] = stack[sp - 1].exp()	}
-				Symbols::FLOG   => { stack[sp - 1] = stack[sp - 1].log(E)	}
-				Symbols::FLOG10 => { stack[sp - 1] = stack[sp - 1].log(10.)}
+				Symbols::FLOG   => { stack[sp - 1] = stack[sp - 1].log(T::E())	}
+				Symbols::FLOG10 => { stack[sp - 1] = stack[sp - 1].log(t(10.))}
 				Symbols::FSQRT  => { stack[sp - 1] = stack[sp - 1].sqrt()	}
-				Symbols::FABS   => { stack[sp - 1] = CF32::new(stack[sp - 1].arg(), 0.) }
-				Symbols::FC     => { sp-=1; stack[sp - 1] = CF32::new(stack[sp - 1 ].re, stack[sp].re)	}
+				Symbols::FINT   => { stack[sp - 1] = complex::new(stack[sp - 1].re.trunc(), stack[sp - 1].im.trunc()) }
+				Symbols::FABS   => { stack[sp - 1] = complex::new(stack[sp - 1].norm(), T::zero()) }
+				Symbols::FC     => { sp-=1; stack[sp - 1] = complex::new(stack[sp - 1 ].re, stack[sp].re)	}
+
+				Symbols::FSINH  => { stack[sp - 1] = stack[sp - 1].sinh()	}
+				Symbols::FCOSH  => { stack[sp - 1] = stack[sp - 1].cosh()	}
+				Symbols::FTANH  => { stack[sp - 1] = stack[sp - 1].tanh()	}
+				Symbols::FASINH => { stack[sp - 1] = stack[sp - 1].asinh()	}
+				Symbols::FACOSH => { stack[sp - 1] = stack[sp - 1].acosh()	}
+				Symbols::FATANH => { stack[sp - 1] = stack[sp - 1].atanh()	}
+				Symbols::FRE    => { stack[sp - 1] = complex::new(stack[sp - 1].re, T::zero()) }
+				Symbols::FIM    => { stack[sp - 1] = complex::new(stack[sp - 1].im, T::zero()) }
+				Symbols::FARG   => { stack[sp - 1] = complex::new(stack[sp - 1].arg(), T::zero()) }
+				Symbols::FCONJ  => { stack[sp - 1] = stack[sp - 1].conj()	}
+				Symbols::FGAMMA => { stack[sp - 1] = eval64(gamma, stack[sp - 1]) }
+				Symbols::FZETA  => { stack[sp - 1] = eval64(zeta, stack[sp - 1]) }
+				Symbols::FPOLAR => { sp-=1; stack[sp - 1] = complex::from_polar(stack[sp - 1].re, stack[sp].re) }
 						
 				Symbols::END | _ => { break }
 			}
 			pc+=1;
 		}
 
-		if sp!=0 { stack[ sp - 1 ]   }
-		else     { CF32::new(0., 0.) }
+		if sp!=0 { stack[ sp - 1 ] }
+		else     { zero }
+	}
+
+
+	fn decode(&self) -> Vec<Ins> {
+		let mut ins = vec![];
+		let mut pc = 0;
+		loop {
+			match Self::u32_2_sym(self.code[pc]) {
+				Symbols::PUSHC | Symbols::PUSHCC => { let (c, words) = self.code_const(pc); ins.push(Ins::Const(c)); pc+=words }
+				Symbols::PUSHI  => ins.push(Ins::Const(CF64::new(0., 1.))),
+				Symbols::PUSHZ  => ins.push(Ins::Z),
+				Symbols::POWI   => { pc+=1; ins.push(Ins::PowI(self.code[pc] as i32)) }
+				Symbols::END 	=> break,
+				sym 			=> ins.push(Ins::Op(sym)),
+			}
+			pc+=1;
+		}
+		ins
+	}
+
+	fn encode(&mut self, ins : &[Ins]) {
+		self.code.clear();
+		for i in ins {
+			match *i {
+				Ins::Const(c) if c.im.to_bits() == 0 => { self.nval = c.re; self.gen(Symbols::PUSHC) } // +0 imaginary only, -0 is kept
+				Ins::Const(c) => { self.code.push(Symbols::PUSHCC as u32); self.push_f64(c.re); self.push_f64(c.im) }
+				Ins::Z 		  => self.gen(Symbols::PUSHZ),
+				Ins::PowI(n)  => { self.code.push(Symbols::POWI as u32); self.code.push(n as u32) }
+				Ins::Op(sym)  => self.gen(sym),
+			}
+		}
+		self.gen(Symbols::END);
+		self.depth = self.stack_depth();
+	}
+
+	// largest stack the code reaches, the interpreters allocate it per evaluation
+	fn stack_depth(&self) -> usize {
+		self.decode().iter().fold((0, 0), |(sp, max), ins| {
+			let sp = match ins {
+				Ins::Op(sym) => sp + 1 - sym.arity(),
+				Ins::PowI(_) => sp,
+				_ 			 => sp + 1,
+			};
+			(sp, max.max(sp))
+		}).1
+	}
+
+	fn fold(&self, ins : &[Ins]) -> CF64 { // constant code evaluated with the interpreter in vm precision
+		let mut vm = ZVm::empty("");
+		vm.encode(ins);
+		match self.precision {
+			Precision::F32 => to_cf64(vm.eval(CF32::new(0., 0.))),
+			Precision::F64 => vm.eval64(CF64::new(0., 0.)),
+		}
+	}
+
+	// constant folding (incl. PUSHC/NEG pairs) and z^n -> POWI n, results are bit identical to the plain code
+	// evaluated in the vm precision
+	pub fn optimize(&mut self) {
+		if self.err.is_some() { return }
+
+		let mut out : Vec<Ins> = vec![];
+		for ins in self.decode() {
+			match ins {
+				Ins::Op(sym) => {
+					let n = sym.arity();
+					let args = &out[out.len() - n..];
+					if args.iter().all(|a| matches!(a, Ins::Const(_))) { // fold with the interpreter itself
+						let c = self.fold(&[args, &[ins]].concat());
+						out.truncate(out.len() - n);
+						out.push(Ins::Const(c));
+					} else if let (Symbols::POWER | Symbols::FPOW, Some(Ins::Const(e))) = (sym, out.last()) {
+						match int_exponent(*e) {
+							Some(n) => { out.pop(); out.push(Ins::PowI(n)) }
+							None 	=> out.push(ins),
+						}
+					} else {
+						out.push(ins)
+					}
+				}
+				Ins::PowI(_) if matches!(out.last(), Some(Ins::Const(_))) => {
+					let c = self.fold(&[*out.last().unwrap(), ins]);
+					*out.last_mut().unwrap() = Ins::Const(c);
+				}
+				_ => out.push(ins),
+			}
+		}
+		self.encode(&out);
+	}
+
+	pub fn closure(&self) -> ZClosure { self.closure_t() }
+
+	// compile code to a closure tree, avoids interpreter dispatch & stack per evaluation
+	pub fn closure_t<T : ZFloat>(&self) -> ZClosure<T> {
+		if self.err.is_some() { return ZClosure { f : Box::new(|_| ct(0., 0.)) } }
+
+		let mut stack : Vec<Node<T>> = vec![];
+		for ins in self.decode() {
+			let node = match ins {
+				Ins::Const(c) => Node::Const(from_cf64(c)),
+				Ins::Z 		  => Node::Z,
+				Ins::PowI(2)  => stack.pop().unwrap().unary(|x| x * x), // powi(2) is x*x
+				Ins::PowI(n)  => stack.pop().unwrap().unary(move |x| x.powi(n)),
+				Ins::Op(sym) if sym.arity() == 2 => {
+					let b = stack.pop().unwrap();
+					let a = stack.pop().unwrap();
+					match sym {
+						Symbols::PLUS  => a.binary(b, |a, b| a + b),
+						Symbols::MINUS => a.binary(b, |a, b| a - b),
+						Symbols::MULT  => a.binary(b, |a, b| a * b),
+						Symbols::DIV   => a.binary(b, |a, b| a / b),
+						Symbols::FC    => a.binary(b, |a, b| complex::new(a.re, b.re)),
+						Symbols::FPOLAR=> a.binary(b, |a, b| complex::from_polar(a.re, b.re)),
+						_ 			   => a.binary(b, |a, b| match int_exponent(b) { Some(n) => a.powi(n), None => a.powc(b) }), // POWER, FPOW
+					}
+				}
+				Ins::Op(sym) => {
+					let a = stack.pop().unwrap();
+					match sym {
+						Symbols::NEG   => a.unary(|x| -x),
+						Symbols::FSIN  => a.unary(|x| x.sin()),
+						Symbols::FCOS  => a.unary(|x| x.cos()),
+						Symbols::FTAN  => a.unary(|x| x.tan()),
+						Symbols::FASIN => a.unary(|x| x.asin()),
+						Symbols::FACOS => a.unary(|x| x.acos()),
+						Symbols::FATAN => a.unary(|x| x.atan()),
+						Symbols::FEXP  => a.unary(|x| x.exp()),
+						Symbols::FLOG  => a.unary(|x| x.log(T::E())),
+						Symbols::FLOG10=> a.unary(|x| x.log(t(10.))),
+						Symbols::FSQRT => a.unary(|x| x.sqrt()),
+						Symbols::FINT  => a.unary(|x| complex::new(x.re.trunc(), x.im.trunc())),
+						Symbols::FABS  => a.unary(|x| complex::new(x.norm(), T::zero())),
+						Symbols::FSINH => a.unary(|x| x.sinh()),
+						Symbols::FCOSH => a.unary(|x| x.cosh()),
+						Symbols::FTANH => a.unary(|x| x.tanh()),
+						Symbols::FASINH=> a.unary(|x| x.asinh()),
+						Symbols::FACOSH=> a.unary(|x| x.acosh()),
+						Symbols::FATANH=> a.unary(|x| x.atanh()),
+						Symbols::FRE   => a.unary(|x| complex::new(x.re, T::zero())),
+						Symbols::FIM   => a.unary(|x| complex::new(x.im, T::zero())),
+						Symbols::FARG  => a.unary(|x| complex::new(x.arg(), T::zero())),
+						Symbols::FCONJ => a.unary(|x| x.conj()),
+						Symbols::FGAMMA=> a.unary(|x| eval64(gamma, x)),
+						Symbols::FZETA => a.unary(|x| eval64(zeta, x)),
+						_ 			   => a, // no eval for this symbol
+					}
+				}
+			};
+			stack.push(node);
+		}
+		ZClosure { f : stack.pop().map_or_else(|| Box::new(|_| ct(0., 0.)) as ZNode<T>, Node::into_closure) }
 	}
 
+	pub fn eval_d(&self, z : CF32) -> (CF32, CF32) { self.eval_d_t(z) }
+
+	// f(z), f'(z) by dual number execution of code, non holomorphic functions (re, im, abs, arg, conj, int, c, polar)
+	// are differentiated along the real axis
+	pub fn eval_d_t<T : ZFloat>(&self, z : complex<T>) -> (complex<T>, complex<T>) {
+		
+		let zero = ct::<T>(0., 0.);
+		if self.err.is_some() { return (zero, zero) }
+
+		let one = ct::<T>(1., 0.);
+		let mut pc : usize = 0;
+		let mut sp : usize = 0;
+		let mut stack : Vec<Dual<T>> = vec![Dual::cons(zero); self.depth];
+
+		loop {
+			let a = if sp > 0 { stack[sp - 1] } else { Dual::cons(zero) };
+			let (x, dx) = (a.v, a.d);
+
+			match Self::u32_2_sym(self.code[pc]) {
+				Symbols::PUSHC | Symbols::PUSHCC => {
+					let (c, words) = self.code_const(pc);
+					stack[sp] = Dual::cons(from_cf64(c));
+					pc+=words;
+					sp+=1
+				}
+				Symbols::PUSHZ => {
+					stack[sp] = Dual::new(z, one);
+					sp+=1
+				}
+				Symbols::PUSHI => {
+					stack[sp] = Dual::cons(ct(0., 1.));
+					sp+=1
+				}
+				Symbols::PLUS  => {	sp-=1;	stack[sp - 1] = stack[sp - 1].add(stack[sp]) }
+				Symbols::MINUS => {	sp-=1;	stack[sp - 1] = stack[sp - 1].sub(stack[sp]) }
+				Symbols::MULT  => {	sp-=1;	stack[sp - 1] = stack[sp - 1].mul(stack[sp]) }
+				Symbols::DIV   => {	sp-=1;	stack[sp - 1] = stack[sp - 1].div(stack[sp]) }
+				Symbols::POWER | Symbols::FPOW => {	sp-=1;	stack[sp - 1] = stack[sp - 1].pow(stack[sp]) }
+				Symbols::POWI => { pc+=1; stack[sp - 1] = a.pow(Dual::cons(ct(self.code[pc] as i32 as f64, 0.))) }
+				Symbols::NEG   => {	stack[sp - 1] = Dual::new(-x, -dx) }
+
+				Symbols::FSIN   => { stack[sp - 1] = a.chain(x.sin(), x.cos()) }
+				Symbols::FCOS   => { stack[sp - 1] = a.chain(x.cos(), -x.sin()) }
+				Symbols::FTAN   => { let t = x.tan(); stack[sp - 1] = a.chain(t, one + t * t) }
+				Symbols::FASIN  => { stack[sp - 1] = a.chain(x.asin(), one / (one - x * x).sqrt()) }
+				Symbols::FACOS  => { stack[sp - 1] = a.chain(x.acos(), -one / (one - x * x).sqrt()) }
+				Symbols::FATAN  => { stack[sp - 1] = a.chain(x.atan(), one / (one + x * x)) }
+				Symbols::FEXP   => { let e = x.exp(); stack[sp - 1] = a.chain(e, e) }
+				Symbols::FLOG   => { stack[sp - 1] = a.chain(x.log(T::E()), one / x) }
+				Symbols::FLOG10 => { stack[sp - 1] = a.chain(x.log(t(10.)), one / (x * T::LN_10())) }
+				Symbols::FSQRT  => { let r = x.sqrt(); stack[sp - 1] = a.chain(r, one / (r * t::<T>(2.))) }
+				Symbols::FINT   => { stack[sp - 1] = Dual::cons(complex::new(x.re.trunc(), x.im.trunc())) }
+				Symbols::FABS   => { let m = x.norm(); stack[sp - 1] = Dual::new(complex::new(m, T::zero()), complex::new((x.conj() * dx).re / m, T::zero())) }
+				Symbols::FC     => { 
+					sp-=1; 
+					let (re, im) = (stack[sp - 1], stack[sp]);
+					stack[sp - 1] = Dual::new(complex::new(re.v.re, im.v.re), complex::new(re.d.re, im.d.re))
+				}
+
+				Symbols::FSINH  => { stack[sp - 1] = a.chain(x.sinh(), x.cosh()) }
+				Symbols::FCOSH  => { stack[sp - 1] = a.chain(x.cosh(), x.sinh()) }
+				Symbols::FTANH  => { let t = x.tanh(); stack[sp - 1] = a.chain(t, one - t * t) }
+				Symbols::FASINH => { stack[sp - 1] = a.chain(x.asinh(), one / (x * x + one).sqrt()) }
+				Symbols::FACOSH => { stack[sp - 1] = a.chain(x.acosh(), one / ((x - one).sqrt() * (x + one).sqrt())) }
+				Symbols::FATANH => { stack[sp - 1] = a.chain(x.atanh(), one / (one - x * x)) }
+				Symbols::FRE    => { stack[sp - 1] = Dual::new(complex::new(x.re, T::zero()), complex::new(dx.re, T::zero())) }
+				Symbols::FIM    => { stack[sp - 1] = Dual::new(complex::new(x.im, T::zero()), complex::new(dx.im, T::zero())) }
+				Symbols::FARG   => { stack[sp - 1] = Dual::new(complex::new(x.arg(), T::zero()), complex::new((dx / x).im, T::zero())) }
+				Symbols::FCONJ  => { stack[sp - 1] = Dual::new(x.conj(), dx.conj()) }
+				Symbols::FGAMMA => { stack[sp - 1] = a.chain(eval64(gamma, x), diff64(gamma, x)) }
+				Symbols::FZETA  => { stack[sp - 1] = a.chain(eval64(zeta, x), diff64(zeta, x)) }
+				Symbols::FPOLAR => { 
+					sp-=1; 
+					let (r, th) = (stack[sp - 1], stack[sp]);
+					let e = complex::from_polar(T::one(), th.v.re);
+					stack[sp - 1] = Dual::new(e * r.v.re, e * complex::new(r.d.re, r.v.re * th.d.re))
+				}
+						
+				Symbols::END | _ => { break }
+			}
+			pc+=1;
+		}
+
+		if sp!=0 { (stack[ sp - 1 ].v, stack[ sp - 1 ].d) }
+		else     { (zero, zero) }
+	}
 	
-	pub fn ok(&self) -> bool { !self.err }
+	pub fn ok(&self) -> bool { self.err.is_none() }
 
 	#[allow(dead_code)]
 	pub fn walk(&mut self) {
@@ -380,8 +895,13 @@ impl ZVm {
 
 			let sym : Symbols = unsafe { ::std::mem::transmute(self.code[i] as u8) };
 			match sym {
-				Symbols::PUSHC => {
-					println!("{:?} {}", sym, Self::u32_to_f32( self.code[i+1] ));
+				Symbols::PUSHC | Symbols::PUSHCC => {
+					let (c, words) = self.code_const(i);
+					println!("{:?} {}", sym, c);
+					i+=words
+				},
+				Symbols::POWI => {
+					println!("{:?} {}", sym, self.code[i+1] as i32);
 					i+=1					
 				},
 				Symbols::END => { 
@@ -395,4 +915,163 @@ impl ZVm {
 			i+=1;			
 		}
 	}
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::f32::consts::PI;
+
+	fn err(source : &str) -> ZError { ZVm::new(source).unwrap_err() }
+
+	#[test]
+	fn compile_errors() {
+		assert!(ZVm::new("z * sin( c(1,1)/cos(3/z) + tan(1/z+1) )").is_ok());
+		assert!(ZVm::new("1e-3 * z").is_ok());
+
+		assert_eq!(err("z + foo(z)"), ZError { pos : 4, expected : "function, constant, z or i".to_string(), found : "foo".to_string() });
+		assert_eq!(err("2 * 1.2.3"),  ZError { pos : 4, expected : "number".to_string(), found : "1.2.3".to_string() });
+		assert_eq!(err("sin(z"),      ZError { pos : 5, expected : "')'".to_string(), found : "".to_string() });
+		assert_eq!(err("sin z"),      ZError { pos : 4, expected : "'('".to_string(), found : "z".to_string() });
+		assert_eq!(err("c(1 2)"),     ZError { pos : 4, expected : "','".to_string(), found : "2".to_string() });
+		assert_eq!(err("z +"),        ZError { pos : 3, expected : "operand".to_string(), found : "".to_string() });
+		assert_eq!(err("z $ 2"),      ZError { pos : 2, expected : "operator or operand".to_string(), found : "$".to_string() });
+		assert_eq!(err("sin(z))"),    ZError { pos : 6, expected : "operator or end of expression".to_string(), found : ")".to_string() });
+		assert_eq!(err(""),           ZError { pos : 0, expected : "operand".to_string(), found : "".to_string() });
+
+		assert_eq!(format!("{}", err("sin(z")), "syntax error at 5: expected ')', found end of expression");
+	}
+
+	fn assert_near(a : CF32, b : CF32, eps : f32) {
+		assert!((a - b).norm() <= eps * (1. + b.norm()), "{} != {}", a, b);
+	}
+
+	#[test]
+	fn functions() {
+		let w = CF32::new(0.7, -1.3);
+		let eval = |source : &str, z : CF32| ZVm::new(source).unwrap().eval(z);
+
+		for z in [CF32::new(0.3, 0.4), CF32::new(-1.2, 2.1), CF32::new(2.5, -0.7)].iter().copied() {
+			assert_near(eval("sinh(z)", z),  z.sinh(), 1e-6);
+			assert_near(eval("cosh(z)", z),  z.cosh(), 1e-6);
+			assert_near(eval("tanh(z)", z),  z.tanh(), 1e-6);
+			assert_near(eval("asinh(z)", z), z.asinh(), 1e-6);
+			assert_near(eval("acosh(z)", z), z.acosh(), 1e-6);
+			assert_near(eval("atanh(z)", z), z.atanh(), 1e-6);
+			assert_near(eval("re(z)", z),    CF32::new(z.re, 0.), 0.);
+			assert_near(eval("im(z)", z),    CF32::new(z.im, 0.), 0.);
+			assert_near(eval("arg(z)", z),   CF32::new(z.arg(), 0.), 0.);
+			assert_near(eval("abs(z)", z),   CF32::new(z.norm(), 0.), 0.);
+			assert_near(eval("conj(z)", z),  z.conj(), 0.);
+			assert_near(eval("-(z^2)", z),   -z * z, 1e-6);
+			assert_near(eval("-z^2", z),     z * z, 1e-6); // unary minus binds tighter
+			assert_near(eval("e^z", z),      z.exp(), 1e-5);
+			assert_near(eval("pow(z, c(0.7, -1.3))", z), z.powc(w), 1e-5);
+			assert_near(eval("pow(z+1, 3)", z), (z + 1.).powi(3), 1e-6);
+			assert_near(eval("polar(abs(z), arg(z))", z), z, 1e-6);
+			assert_near(eval("polar(2, pi/3)", z), CF32::from_polar(2., PI / 3.), 1e-6);
+		}
+	}
+
+	#[test]
+	fn derivatives() { // dual numbers vs central difference
+		let z = CF32::new(0.6, 0.45);
+		let (f, df) = ZVm::new("z^3 - 2*z + 1").unwrap().eval_d(z);
+		assert_near(f, z * z * z - 2. * z + 1., 1e-6);
+		assert_near(df, 3. * z * z - 2., 1e-6);
+
+		for source in ["sin(z)*cos(1/z)", "tan(z)+exp(-z)/log(z+2)", "sqrt(z)*asin(z)-acos(z)*atan(z)", "log10(z)", 
+			"sinh(z)*cosh(z)+tanh(z)", "asinh(z)+acosh(z+2)+atanh(z)", "(z+1)^c(0.5,1)", "pow(2, z)", "pow(z, z)",
+			"gamma(z+1)", "zeta(z+2)", "c(1,2)*z^6-1"].iter() {
+			let zvm = ZVm::new(source).unwrap();
+			let h = 1e-3;
+			let num_d = (zvm.eval(z + h) - zvm.eval(z - h)) / (2. * h);
+			let (f, df) = zvm.eval_d(z);
+			assert_near(f, zvm.eval(z), 1e-6);
+			assert_near(df, num_d, 1e-3);
+		}
+	}
+
+	#[test]
+	fn deep_nesting() { // stack sized from the code, z+(z+(..)) keeps one z per level
+		let source = format!("{}z{}", "z+(".repeat(40), ")".repeat(40));
+		let zvm = ZVm::new(&source).unwrap();
+		let z = CF32::new(0.5, -0.25);
+		assert_eq!(zvm.depth, 41);
+		assert_near(zvm.eval(z), z * 41., 1e-5);
+		assert_near(zvm.eval_d(z).1, CF32::new(41., 0.), 1e-5);
+		assert_eq!(zvm.eval64(CF64::new(1., 0.)), CF64::new(41., 0.));
+		assert_eq!((ZVm::new("1+2*3").unwrap().depth, ZVm::default().depth), (1, 0)); // folded
+	}
+
+	fn same(a : CF32, b : CF32) -> bool {
+		fn same_f32(a : f32, b : f32) -> bool { a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan()) }
+		same_f32(a.re, b.re) && same_f32(a.im, b.im)
+	}
+
+	#[test]
+	fn optimize() {
+		let zvm = ZVm::new("z*(c(1,2)*2^3 + pi/4 - (-2)) + sin(i)").unwrap();
+		assert_eq!(zvm.decode().len(), 5); // z c * c +
+
+		let zvm = ZVm::new("-(1/z)^3 + z^-2").unwrap();
+		assert!(zvm.decode().contains(&Ins::PowI(3)) && zvm.decode().contains(&Ins::PowI(-2)));
+		assert!(zvm.decode().iter().all(|ins| *ins != Ins::Op(Symbols::POWER)));
+	}
+
+	// unoptimized interpreter vs optimized interpreter & closure tree, bit identical results
+	fn engines(n : usize, timed : bool) {
+		use std::time::Instant;
+
+		let zs : Vec<CF32> = (0..n * n).map(|i| CF32::new(-3. + 6. * (i % n) as f32 / n as f32, -3. + 6. * (i / n) as f32 / n as f32)).collect();
+
+		for source in ["z * sin( c(1,1)/cos(3/z) + tan(1/z+1) )", "(z^2-1) * (z-c(2,1))^2 / (z^2+c(2,1))", "acos(c(1,2)*log(sin(z^3-1)/z))",
+			"(z/2)^2*(z+c(1,2))*(z+c(2,2))/z^3", "-z^2 + sqrt(-4) * z - log(-1) + pow(z, 2.5) + polar(2, pi/3)", "gamma(z) + zeta(z) + conj(z)*abs(z)"].iter() {
+			let mut raw = ZVm::empty(source);
+			raw.compile().unwrap();
+			let opt = ZVm::new(source).unwrap();
+			let closure = opt.closure();
+
+			let t = Instant::now();
+			let r_raw : Vec<CF32> = zs.iter().map(|z| raw.eval(*z)).collect();
+			let t_raw = Instant::now() - t;
+
+			let t = Instant::now();
+			let r_opt : Vec<CF32> = zs.iter().map(|z| opt.eval(*z)).collect();
+			let t_opt = Instant::now() - t;
+
+			let t = Instant::now();
+			let r_closure : Vec<CF32> = zs.iter().map(|z| closure.eval(*z)).collect();
+			let t_closure = Instant::now() - t;
+
+			if timed { println!("{:50} eval: {:?}, optimized: {:?}, closure: {:?}", source, t_raw, t_opt, t_closure) }
+			for i in 0..zs.len() {
+				assert!(same(r_raw[i], r_opt[i]) && same(r_raw[i], r_closure[i]), "{} at {}: {} {} {}", source, zs[i], r_raw[i], r_opt[i], r_closure[i]);
+			}
+		}
+	}
+
+	#[test]
+	fn closure() { engines(32, false) }
+
+	#[test]
+	#[ignore] // cargo test --release closure_bench -- --ignored --nocapture
+	fn closure_bench() { engines(256, true) }
+
+	#[test]
+	fn gamma_zeta() {
+		let eval = |source : &str| ZVm::new(source).unwrap().eval(CF32::new(0., 0.));
+
+		assert_near(eval("gamma(5)"), CF32::new(24., 0.), 1e-6);
+		assert_near(eval("gamma(0.5)"), CF32::new(PI.sqrt(), 0.), 1e-6);
+		assert_near(eval("gamma(c(1,1))"), CF32::new(0.49801566, -0.15494983), 1e-6);
+		assert_near(eval("gamma(-0.5)"), CF32::new(-2. * PI.sqrt(), 0.), 1e-6);
+
+		assert_near(eval("zeta(2)"), CF32::new(PI * PI / 6., 0.), 1e-6);
+		assert_near(eval("zeta(0)"), CF32::new(-0.5, 0.), 1e-6);
+		assert_near(eval("zeta(-1)"), CF32::new(-1. / 12., 0.), 1e-6);
+		assert_near(eval("zeta(-2)"), CF32::new(0., 0.), 1e-6);
+		assert_near(eval("zeta(c(0.5, 14.134725))"), CF32::new(0., 0.), 1e-5); // first nontrivial zero
+		assert_near(eval("zeta(c(2, 3))"), CF32::new(0.79802199, -0.11374431), 1e-6);
+	}
+}
@@ -0,0 +1,73 @@
+// domain coloring colors & main, appended to a shader defining domain_color_func:
+// dc.shader or the ZVm Dialect::Glsl prelude of a z expression
+
+uint argbf2uint(uint alpha, float r, float g, float b) { 
+	return (alpha << 24)         |
+	(uint(255.*r)  & 0xffu     ) |  
+	((uint(255.*g) & 0xffu)<<8 ) | 
+	((uint(255.*b) & 0xffu)<<16) ;
+}
+
+uint rgbf2uint(vec3 v) { 
+	v*=255.;
+	return 0xff000000u        | // alpha 0xff
+	( uint(v.r) & 0xffu     ) |  
+	((uint(v.g) & 0xffu)<<8 ) | 
+	((uint(v.b) & 0xffu)<<16) ;
+}
+		
+uint HSV2int(float h, float s, float v) { // convert hsv to int with alpha 0xff00000
+	float r = 0, g = 0, b = 0;
+	
+	if (s == 0) r = g = b = v;
+	else {
+		if (h == 1)  h = 0;
+		
+		float z = floor(h * 6.),
+			f = h * 6 - z,
+			p = v * (1 - s), q = v * (1 - s * f),
+			t = v * (1 - s * (1 - f));
+		
+		return rgbf2uint( vec3[]( vec3(v,t,p), vec3(q,v,p), vec3(p,v,t), 
+								  vec3(p,q,v), vec3(t,p,v), vec3(v,p,q) ) [int(z) % 6] );
+	}
+	return rgbf2uint(vec3(r, g, b));
+}
+
+vec2 domain_color_func(vec2); // domain coloring func
+
+uint dc_get_color(int x, int y, int w, int h) {
+
+	const float E = 2.7182818284590452353602874713527,
+				M_PI = 3.141592653589793238462643383,
+				PI = M_PI, PI2 = PI * 2.;
+	
+	const float limit=PI,  rmi = -limit, rma = limit, imi = -limit, ima = limit;
+	
+	vec2 z = vec2( ima - (ima - imi) * y / (h - 1),  rma - (rma - rmi) * x / (w - 1) );
+	
+	vec2 v = domain_color_func(z); // evaluate domain coloring func
+	
+	
+	float 	m, ranges, rangee; //  prop. e^n < m < e^(n-1)
+	for (m=length(v), ranges=0, rangee=1; m > rangee; rangee *= E) ranges = rangee;
+	
+	float 	k  = (m - ranges) / (rangee - ranges),
+		  	kk = (k < 0.5 ? k * 2. : 1. - (k - 0.5) * 2);
+	
+	float 	ang = mod(abs(atan(v.y, v.x)), PI2) / PI2,    // -> hsv
+			sat = 0.4 + (1. - pow(1. - kk, 3.))       * 0.6,
+			val = 0.6 + (1. - pow(1. - (1 - kk), 3.)) * 0.4;
+	
+	return HSV2int(ang, sat, val);
+}            
+
+out uint out_color; // RGBA
+
+uniform ivec2 size;
+
+void main() {
+	int x = gl_VertexID % size.x, y = gl_VertexID /  size.x;
+	
+	out_color = dc_get_color(x, y, size.x, size.y);
+}
\ No newline at end of file
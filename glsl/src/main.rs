@@ -9,6 +9,11 @@ use rand::prelude::*;
 use std::cell::RefCell;
 use image::{ImageBuffer, Rgba};
 
+#[allow(dead_code)]
+#[path = "../../ocl/src/zvm.rs"]
+mod zvm;
+use zvm::{ZVm, Dialect};
+
 // main image struct 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Image { out_color: u32  }
@@ -17,7 +22,10 @@ fn main() {
     // image vertex w/ out_color
     implement_vertex!(Image, out_color);
 
-    voronoi()
+    match std::env::args().nth(1) { // glsl [z expression]: domain coloring of the expression
+        Some(expr) => domain_coloring_src(&expr),
+        None => voronoi(),
+    }
 }
 
 #[allow(dead_code)]
@@ -116,13 +124,31 @@ fn render_voronoi(size : i32, n_points : i32, points : &Vec<f32>, shader : &str)
 fn domain_coloring() {
     let size = 1000;
     let t = Instant::now();
-    if let Ok(image) = render_dc(size, include_str!("dc.shader")) { // read result & write to flat bin file (ARGB) u32 size x size
+    if let Ok(image) = render_dc(size, concat!(include_str!("dc.shader"), include_str!("dc_color.shader"))) { // read result & write to flat bin file (ARGB) u32 size x size
         println!("dc {}x{}={}, lap:{:.0} ms -> generated dc.png", size, size, size*size, (Instant::now()-t).as_millis());
   
         write_png("dc.png", &image)
     }
 }
 
+// z expression transpiled to glsl
+fn domain_coloring_src(expr : &str) {
+    let zvm = match ZVm::new(expr) {
+        Ok(zvm) => zvm,
+        Err(err) => { eprintln!("z expression {}", err); return }
+    };
+    let shader = format!("#version 330\n{}\nvec2 domain_color_func(vec2 z) {{ return {}; }}\n{}",
+        Dialect::Glsl.prelude(), zvm.to_source(Dialect::Glsl), include_str!("dc_color.shader"));
+
+    let size = 1000;
+    let t = Instant::now();
+    if let Ok(image) = render_dc(size, &shader) {
+        println!("dc {} {}x{}={}, lap:{:.0} ms -> generated dc.png", expr, size, size, size*size, (Instant::now()-t).as_millis());
+  
+        write_png("dc.png", &image)
+    }
+}
+
 fn render_dc(size : i32, shader : &str) -> Result<Vec<Image>, glium::buffer::ReadError>{
 
     let n_pnts = (size * size) as usize;
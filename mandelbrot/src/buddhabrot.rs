@@ -0,0 +1,267 @@
+/*
+	orbit density renders of z*z + c, the image is the histogram of orbit points z in the viewport
+
+		buddhabrot 		orbits escaping within the iteration limit
+		anti buddhabrot orbits that don't escape
+		nebulabrot 		one limit per rgb channel
+
+	samples c run in chunks, each chunk accumulates in its own buffer (rayon fold) merged at the end (reduce).
+	metropolis-hastings: every chunk is a markov chain over c with stationary density ~ number of orbit points
+	in view, small mutations or random restarts. each sample is splatted with weight 1/contribution so the
+	density stays that of uniform sampling, only with far more samples where the view is.
+*/
+
+use crate::mandelbrot::*;
+use num::complex::Complex64;
+use rayon::prelude::*;
+
+const SAMPLE_RANGE : f64 = 2.; // c sampled in [-2,2]x[-2,2]
+const CHUNK_SAMPLES : u64 = 10_000;
+const RESTART_PROB : f64 = 0.2; // metropolis proposal: uniform restart, else mutation
+const MUTATION : f64 = 0.05; // mutation size relative to the view width
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMap {
+	Log,
+	Gamma(f32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Buddhabrot {
+	pub limits 		: [u32; 3], // iteration limit per rgb channel
+	pub samples 	: u64,
+	pub anti 		: bool,
+	pub metropolis 	: bool,
+	pub tone 		: ToneMap,
+	pub seed 		: u64,
+}
+
+// histogram target: viewport & image size
+#[derive(Clone, Copy)]
+struct Frame<'a> {
+	vp : &'a Viewport,
+	w  : u32,
+	h  : u32,
+}
+
+impl<'a> Frame<'a> {
+	fn index(&self, z : Complex64) -> Option<usize> { self.vp.c_to_pixel(z, self.w, self.h).map(|(x, y)| (y * self.w + x) as usize) }
+}
+
+// xorshift64*, one stream per chunk
+struct Rng(u64);
+
+impl Rng {
+	fn new(seed : u64) -> Self { Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1) }
+
+	fn next_f64(&mut self) -> f64 { // 0..1
+		self.0 ^= self.0 >> 12;
+		self.0 ^= self.0 << 25;
+		self.0 ^= self.0 >> 27;
+		(self.0.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11) as f64 / (1_u64 << 53) as f64
+	}
+
+	fn range(&mut self, r : f64) -> f64 { (2. * self.next_f64() - 1.) * r }
+
+	fn uniform_c(&mut self) -> Complex64 { Complex64::new(self.range(SAMPLE_RANGE), self.range(SAMPLE_RANGE)) }
+
+	fn gaussian(&mut self) -> f64 { // box-muller
+		(-2. * (1. - self.next_f64()).ln()).sqrt() * (std::f64::consts::PI * 2. * self.next_f64()).cos()
+	}
+}
+
+impl Buddhabrot {
+	pub fn new(limit : u32) -> Self { Self::nebulabrot([limit; 3]) }
+
+	pub fn nebulabrot(limits : [u32; 3]) -> Self {
+		Self { limits, samples : 1_000_000, anti : false, metropolis : false, tone : ToneMap::Log, seed : 1 }
+	}
+
+	pub fn with_samples(mut self, samples : u64) -> Self {
+		self.samples = samples;
+		self
+	}
+
+	pub fn with_anti(mut self, anti : bool) -> Self {
+		self.anti = anti;
+		self
+	}
+
+	pub fn with_metropolis(mut self, metropolis : bool) -> Self {
+		self.metropolis = metropolis;
+		self
+	}
+
+	pub fn with_tone(mut self, tone : ToneMap) -> Self {
+		self.tone = tone;
+		self
+	}
+
+	pub fn with_seed(mut self, seed : u64) -> Self {
+		self.seed = seed;
+		self
+	}
+
+	fn max_limit(&self) -> u32 { *self.limits.iter().max().unwrap() }
+
+	// orbit z1, z2.. of c into 'orbit', returns the escape index
+	fn orbit(&self, c : Complex64, orbit : &mut Vec<Complex64>) -> Option<usize> {
+		orbit.clear();
+		if !self.anti && MandelbrotSet.interior(ComplexF32::new(c.re as f32, c.im as f32)) { return None } // nothing to draw
+		let mut z = c;
+		for n in 0..self.max_limit() as usize {
+			orbit.push(z);
+			if z.norm_sqr() > 4. { return Some(n) }
+			z = z * z + c;
+		}
+		None
+	}
+
+	// orbit points drawn on channel k
+	fn points<'a>(&self, orbit : &'a [Complex64], escape : Option<usize>, k : usize) -> &'a [Complex64] {
+		let limit = self.limits[k] as usize;
+		match (escape, self.anti) {
+			(Some(n), false) if n < limit => &orbit[..n],
+			(Some(n), true) if n >= limit => &orbit[..limit],
+			(None, true) => &orbit[..limit.min(orbit.len())],
+			_ => &[],
+		}
+	}
+
+	fn splat(&self, frame : Frame, orbit : &[Complex64], escape : Option<usize>, weight : f32, hist : &mut [Vec<f32>; 3]) {
+		for (k, channel) in hist.iter_mut().enumerate() {
+			for z in self.points(orbit, escape, k) {
+				if let Some(index) = frame.index(*z) { channel[index] += weight }
+			}
+		}
+	}
+
+	fn contribution(&self, frame : Frame, orbit : &[Complex64], escape : Option<usize>) -> usize {
+		(0..3).map(|k| self.points(orbit, escape, k).iter().filter(|z| frame.index(**z).is_some()).count()).sum()
+	}
+
+	fn run_chunk(&self, frame : Frame, chunk : u64, samples : u64, hist : &mut [Vec<f32>; 3]) {
+		let mut rng = Rng::new(self.seed ^ (chunk + 1).wrapping_mul(0xD6E8_FEB8_6659_FD93));
+		let mut orbit = Vec::with_capacity(self.max_limit() as usize);
+
+		if !self.metropolis {
+			for _ in 0..samples {
+				let escape = self.orbit(rng.uniform_c(), &mut orbit);
+				self.splat(frame, &orbit, escape, 1., hist);
+			}
+			return
+		}
+
+		// chain start: a sample that reaches the view, give up on hopeless views
+		let mut current = None;
+		for _ in 0..samples {
+			let c = rng.uniform_c();
+			let escape = self.orbit(c, &mut orbit);
+			let contrib = self.contribution(frame, &orbit, escape);
+			if contrib > 0 { current = Some((c, contrib, escape)); break }
+		}
+		let (mut c, mut contrib, mut escape) = match current { Some(s) => s, None => return };
+
+		let step = frame.vp.width * MUTATION;
+		let mut proposal = Vec::with_capacity(orbit.capacity());
+		for _ in 0..samples {
+			let c1 = if rng.next_f64() < RESTART_PROB { rng.uniform_c() } else { c + Complex64::new(rng.gaussian(), rng.gaussian()) * step };
+			let escape1 = self.orbit(c1, &mut proposal);
+			let contrib1 = self.contribution(frame, &proposal, escape1);
+
+			if contrib1 > 0 && rng.next_f64() < contrib1 as f64 / contrib as f64 {
+				std::mem::swap(&mut orbit, &mut proposal);
+				c = c1;
+				contrib = contrib1;
+				escape = escape1;
+			}
+			self.splat(frame, &orbit, escape, 1. / contrib as f32, hist);
+		}
+	}
+
+	// per channel orbit density over the image
+	pub fn density(&self, vp : &Viewport, w : u32, h : u32) -> [Vec<f32>; 3] {
+		let size = (w * h) as usize;
+		let empty = || [vec![0_f32; size], vec![0_f32; size], vec![0_f32; size]];
+		let (chunks, frame) = (self.samples.div_ceil(CHUNK_SAMPLES), Frame { vp, w, h });
+
+		(0..chunks).into_par_iter()
+			.fold(empty, |mut hist, chunk| {
+				self.run_chunk(frame, chunk, CHUNK_SAMPLES.min(self.samples - chunk * CHUNK_SAMPLES), &mut hist);
+				hist
+			})
+			.reduce(empty, |mut a, b| {
+				for (ca, cb) in a.iter_mut().zip(b.iter()) {
+					for (x, y) in ca.iter_mut().zip(cb.iter()) { *x += y }
+				}
+				a
+			})
+	}
+
+	// each channel normalized to its max, log relative to the smallest density so weights don't matter
+	pub fn tone_map(&self, hist : &[Vec<f32>; 3]) -> Vec<u32> {
+		let maps : Vec<Vec<u8>> = hist.iter().map(|channel| {
+			let max = channel.iter().cloned().fold(0., f32::max);
+			let min = channel.iter().cloned().filter(|&v| v > 0.).fold(max, f32::min);
+			channel.iter().map(|&v| {
+				if max <= 0. { return 0 }
+				let t = match self.tone {
+					ToneMap::Log 		=> (1. + v / min).ln() / (1. + max / min).ln(),
+					ToneMap::Gamma(g) 	=> (v / max).powf(1. / g),
+				};
+				(t * 255.).round().min(255.) as u8
+			}).collect()
+		}).collect();
+
+		(0..maps[0].len()).map(|i| rgb([maps[0][i], maps[1][i], maps[2][i]])).collect()
+	}
+}
+
+impl Mandelbrot {
+	// orbit density render into image, uses viewport & size only
+	pub fn generate_buddhabrot(&mut self, b : &Buddhabrot) {
+		let hist = b.density(&self.viewport, self.w, self.h);
+		self.image = b.tone_map(&hist);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn hits(hist : &[Vec<f32>; 3]) -> usize { hist[0].iter().filter(|&&v| v > 0.).count() }
+
+	#[test]
+	fn buddhabrot() {
+		let (w, h, vp) = (64, 64, Viewport::new(Complex64::new(-0.5, 0.), 3.));
+		let b = Buddhabrot::new(200).with_samples(20_000);
+		let hist = b.density(&vp, w, h);
+		assert_eq!(hist, b.density(&vp, w, h)); // seeded, chunk order independent
+		assert!(hits(&hist) > 500);
+
+		// symmetric about the real axis, rows j & h-1-j
+		let (top, bottom) : (f32, f32) = (hist[0][..(w * h / 2) as usize].iter().sum(), hist[0][(w * h / 2) as usize..].iter().sum());
+		assert!((top / bottom - 1.).abs() < 0.1, "{} {}", top, bottom);
+
+		let anti = b.with_anti(true).density(&vp, w, h);
+		let inside = |p : usize| MandelbrotSet.escape(ComplexF32::new(vp.pixel_to_c(p as u32 % w, p as u32 / w, w, h).re as f32, vp.pixel_to_c(p as u32 % w, p as u32 / w, w, h).im as f32), 200, 2.).iters == 200;
+		let (total, set) = (anti[0].iter().sum::<f32>(), anti[0].iter().enumerate().filter(|(p, _)| inside(*p)).map(|(_, v)| v).sum::<f32>());
+		assert!(set / total > 0.9, "anti buddhabrot orbits stay in the set: {}", set / total);
+
+		let neb = Buddhabrot::nebulabrot([500, 100, 20]).with_samples(20_000).density(&vp, w, h);
+		let sums : Vec<f32> = neb.iter().map(|c| c.iter().sum()).collect();
+		assert!(sums[0] > sums[1] && sums[1] > sums[2]);
+
+		let image = b.with_tone(ToneMap::Gamma(2.)).tone_map(&hist);
+		assert_eq!(image.len(), (w * h) as usize);
+		assert!(image.iter().any(|&c| rgb_components(c)[0] == 255.));
+	}
+
+	#[test]
+	fn metropolis() { // a small view off the main body: importance sampling finds far more orbits
+		let (w, h, vp) = (32, 32, Viewport::new(Complex64::new(-0.1, 0.95), 0.02));
+		let b = Buddhabrot::new(500).with_samples(20_000);
+		let (uniform, mh) = (b.density(&vp, w, h), b.with_metropolis(true).density(&vp, w, h));
+		assert!(hits(&mh) > 2 * hits(&uniform), "{} {}", hits(&mh), hits(&uniform));
+	}
+}
@@ -0,0 +1,230 @@
+/*
+	exterior distance estimation, the derivative dz/dp is iterated with z (families with a d_step)
+		d = |z| ln|z| / |dz|
+	is within a factor 4 of the distance to the set, in the complex plane.
+
+	shading
+		distance 	gray by distance in pixels, filaments thinner than a pixel stay visible
+		slope 		palette lit by a light at LIGHT_ANGLE above the potential surface, normal z/dz
+
+	heightfields: distance or smooth iteration field in 0..1, interior 1, as 16 bit png or obj solid
+*/
+
+use crate::mandelbrot::*;
+use image::{ImageBuffer, Luma};
+use num::complex::Complex;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+const LIGHT_ANGLE : f32 = std::f32::consts::FRAC_PI_4; // direction in the image plane
+const LIGHT_HEIGHT : f32 = 1.5;
+const OBJ_SIZE : f32 = 100.; // mm, longest side of the solid
+const OBJ_BASE : f32 = 2.; // mm, floor under the lowest point
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shading {
+	Palette,
+	Distance,
+	Slope,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HeightField {
+	Distance,
+	Smooth,
+}
+
+// escaped pixel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Exterior {
+	pub count 	 : f32, // smooth iteration count
+	pub distance : f32, // to the set
+	pub normal 	 : ComplexF32, // unit, zero where dz overflowed
+}
+
+impl Mandelbrot {
+	pub fn with_shading(mut self, shading : Shading) -> Self {
+		self.shading = shading;
+		self
+	}
+
+	// deep zooms run the perturbation loop without derivative
+	pub fn has_distance(&self) -> bool {
+		self.deep.is_none() && self.fractal.d_step(ComplexF32::new(0., 0.), ComplexF32::new(1., 0.)).is_some()
+	}
+
+	pub fn pixel_size(&self) -> f32 { (self.viewport.width / self.whole_frame().w as f64) as f32 }
+
+	fn pixel_exterior(&self, i : u32, j : u32, bailout : f32) -> Option<Exterior> {
+		let c = self.pixel_to_c(i, j);
+		let (e, dz) = self.fractal.escape_d(ComplexF32::new(c.re as f32, c.im as f32), self.iters, bailout)?;
+		if e.iters >= self.iters { return None }
+
+		let u = e.z / dz;
+		let normal = if u.norm().is_normal() { u / u.norm() } else { ComplexF32::new(0., 0.) };
+		Some(Exterior { count : self.fractal.smooth(&e, bailout), distance : self.fractal.distance(&e, dz).max(0.), normal })
+	}
+
+	// None per pixel inside the set, Err for families without derivative
+	pub fn exterior_field(&self) -> Result<Vec<Option<Exterior>>, String> {
+		if !self.has_distance() { return Err("distance estimation needs z*z + c like families, no deep zoom".to_string()) }
+		let bailout = self.fractal.bailout().max(SMOOTH_BAILOUT);
+		Ok((0..self.w * self.h).into_par_iter().map(|index| self.pixel_exterior(index % self.w, index / self.w, bailout)).collect())
+	}
+
+	// image for distance & slope shading, palette coloring for families without derivative
+	pub(crate) fn shade(&self) -> Vec<u32> {
+		let field = match self.exterior_field() {
+			Ok(field) if self.shading != Shading::Palette => field,
+			_ => return self.colorize(&self.escape_counts()),
+		};
+		let pixel = self.pixel_size();
+
+		if self.shading == Shading::Distance {
+			return field.par_iter().map(|e| match e {
+				Some(e) => { let g = ((e.distance / pixel).sqrt().min(1.) * 255.) as u8; rgb([g, g, g]) }
+				None => 0,
+			}).collect()
+		}
+
+		let counts : Vec<Option<f32>> = field.iter().map(|e| e.map(|e| e.count)).collect();
+		let light = Complex::from_polar(1., LIGHT_ANGLE);
+		self.colorize(&counts).into_par_iter().zip(field.par_iter()).map(|(color, e)| match e {
+			Some(e) => {
+				let t = ((e.normal.re * light.re + e.normal.im * light.im + LIGHT_HEIGHT) / (1. + LIGHT_HEIGHT)).max(0.);
+				let c = rgb_components(color);
+				rgb([(c[0] * t) as u8, (c[1] * t) as u8, (c[2] * t) as u8])
+			}
+			None => color,
+		}).collect()
+	}
+
+	// 0 at the farthest pixel, 1 on and inside the set
+	pub fn height_field(&self, kind : HeightField) -> Result<Vec<f32>, String> {
+		let values : Vec<Option<f32>> = match kind {
+			HeightField::Distance => {
+				let pixel = self.pixel_size();
+				self.exterior_field()?.into_iter().map(|e| e.map(|e| (1. + e.distance / pixel).ln())).collect()
+			}
+			HeightField::Smooth => {
+				let bailout = self.fractal.bailout().max(SMOOTH_BAILOUT);
+				(0..self.w * self.h).into_par_iter().map(|index| {
+					let c = self.pixel_to_c(index % self.w, index / self.w);
+					let e = self.fractal.escape(ComplexF32::new(c.re as f32, c.im as f32), self.iters, bailout);
+					if e.iters >= self.iters { None } else { Some(-self.fractal.smooth(&e, bailout)) }
+				}).collect()
+			}
+		};
+
+		let (lo, hi) = values.iter().flatten().fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+		Ok(values.iter().map(|v| match v {
+			Some(v) if hi > lo => (hi - v) / (hi - lo),
+			Some(_) => 0.,
+			None => 1.,
+		}).collect())
+	}
+
+	pub fn write_heightmap(&self, name : &str, kind : HeightField) -> Result<(), String> {
+		let field = self.height_field(kind)?;
+		let imgbuf = ImageBuffer::<Luma<u16>, Vec<u16>>::from_fn(self.w, self.h, |x, y| Luma([(field[(y * self.w + x) as usize] * 65535.).round() as u16]));
+		imgbuf.save(name).map_err(|err| format!("{}: {}", name, err))
+	}
+
+	// closed solid for 3d printing: heightfield sampled every 'step' pixels on top, walls & a flat bottom, mm units
+	pub fn write_obj(&self, name : &str, kind : HeightField, relief : f32, step : u32) -> Result<(), String> {
+		let field = self.height_field(kind)?;
+		write_solid(name, &field, self.w, self.h, relief, step.max(1)).map_err(|err| format!("{}: {}", name, err))
+	}
+}
+
+fn write_solid(name : &str, field : &[f32], w : u32, h : u32, relief : f32, step : u32) -> io::Result<()> {
+	if w <= step || h <= step { // at least 2 x 2 samples
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} x {} pixels, step {}: too small for a solid", w, h, step)))
+	}
+	let (nx, ny) = ((w - 1) / step + 1, (h - 1) / step + 1);
+	let scale = OBJ_SIZE / (w.max(h) - 1).max(1) as f32;
+	let mut out = BufWriter::new(File::create(name)?);
+	let top = |i : u32, j : u32| j * nx + i + 1; // obj indices start at 1
+
+	writeln!(out, "# mandelbrot heightfield {} x {}", nx, ny)?;
+	for j in 0..ny {
+		for i in 0..nx {
+			let z = OBJ_BASE + relief * field[(j * step * w + i * step) as usize];
+			writeln!(out, "v {} {} {}", (i * step) as f32 * scale, ((h - 1 - j * step) as f32) * scale, z)?;
+		}
+	}
+	for j in 0..ny - 1 {
+		for i in 0..nx - 1 { // counterclockwise seen from above, image rows go down
+			writeln!(out, "f {} {} {}", top(i, j + 1), top(i + 1, j + 1), top(i + 1, j))?;
+			writeln!(out, "f {} {} {}", top(i, j + 1), top(i + 1, j), top(i, j))?;
+		}
+	}
+
+	// border counterclockwise seen from above, starting at the bottom left corner
+	let ring : Vec<u32> = (0..nx - 1).map(|i| top(i, ny - 1))
+		.chain((1..ny).rev().map(|j| top(nx - 1, j)))
+		.chain((1..nx).rev().map(|i| top(i, 0)))
+		.chain((0..ny - 1).map(|j| top(0, j)))
+		.collect();
+	let floor = nx * ny; // floor vertex k + 1 under ring[k]
+	for &v in ring.iter() {
+		let (i, j) = ((v - 1) % nx, (v - 1) / nx);
+		writeln!(out, "v {} {} 0", (i * step) as f32 * scale, ((h - 1 - j * step) as f32) * scale)?;
+	}
+	let center = floor + ring.len() as u32 + 1;
+	writeln!(out, "v {} {} 0", ((nx - 1) * step) as f32 * scale / 2., ((h - 1) as f32 - ((ny - 1) * step) as f32 / 2.) * scale)?;
+
+	for k in 0..ring.len() {
+		let k1 = (k + 1) % ring.len();
+		let (a, b, fa, fb) = (ring[k], ring[k1], floor + k as u32 + 1, floor + k1 as u32 + 1);
+		writeln!(out, "f {} {} {}", fa, fb, b)?;
+		writeln!(out, "f {} {} {}", fa, b, a)?;
+		writeln!(out, "f {} {} {}", center, fb, fa)?;
+	}
+	out.flush()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use num::complex::Complex64;
+	use std::collections::HashMap;
+
+	#[test]
+	fn heightfield() {
+		let (w, h) = (48, 32);
+		let mnd = Mandelbrot::new(w, h, Viewport::new(Complex64::new(-0.75, 0.1), 0.5), 500);
+		for &kind in [HeightField::Distance, HeightField::Smooth].iter() {
+			let field = mnd.height_field(kind).unwrap();
+			assert!(field.iter().all(|&v| (0. ..=1.).contains(&v)));
+			assert!(field.contains(&0.) && field.contains(&1.));
+		}
+		assert!(mnd.with_fractal(BurningShip).height_field(HeightField::Distance).is_err());
+
+		let mnd = Mandelbrot::new(w, h, Viewport::new(Complex64::new(-0.75, 0.1), 0.5), 500);
+		let dir = std::env::temp_dir();
+		let png = dir.join("mandel_heightfield_test.png");
+		mnd.write_heightmap(png.to_str().unwrap(), HeightField::Distance).unwrap();
+		let img = image::open(&png).unwrap().into_luma16();
+		assert_eq!(img.dimensions(), (w, h));
+		assert_eq!(img.pixels().map(|p| p[0]).max(), Some(65535));
+
+		// watertight: every edge is shared by 2 faces in opposite directions
+		let obj = dir.join("mandel_heightfield_test.obj");
+		mnd.write_obj(obj.to_str().unwrap(), HeightField::Smooth, 10., 3).unwrap();
+		let text = std::fs::read_to_string(&obj).unwrap();
+		let vertices = text.lines().filter(|l| l.starts_with("v ")).count();
+		let mut edges = HashMap::new();
+		for l in text.lines().filter(|l| l.starts_with("f ")) {
+			let f : Vec<usize> = l[2..].split(' ').map(|v| v.parse().unwrap()).collect();
+			assert!(f.iter().all(|&v| v >= 1 && v <= vertices));
+			for k in 0..3 { *edges.entry((f[k], f[(k + 1) % 3])).or_insert(0) += 1 }
+		}
+		assert!(edges.iter().all(|(&(a, b), &n)| n == 1 && edges.get(&(b, a)) == Some(&1)));
+		assert!(mnd.write_obj(obj.to_str().unwrap(), HeightField::Smooth, 10., 32).is_err());
+		assert!(write_solid(obj.to_str().unwrap(), &[], 0, 0, 10., 1).is_err());
+		let _ = std::fs::remove_file(png);
+		let _ = std::fs::remove_file(obj);
+	}
+}
@@ -0,0 +1,248 @@
+/*
+	escape time fractal families
+
+	a family only supplies the iteration step, pixel loop, palette & png are shared by Mandelbrot
+		z(n+1) = step(z(n), z(n-1), c), z(0) = z0(p), c = c(p), p : pixel point
+	holomorphic families also supply d_step for the derivative dz/dp, used by distance estimation
+*/
+
+use num::complex::*;
+use std::fmt;
+
+pub type ComplexF32 = Complex<f32>;
+
+// orbit end, iters == max iterations when it doesn't escape
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Escape {
+	pub iters : u32,
+	pub z 	  : ComplexF32,
+}
+
+pub trait EscapeTime : Sync + Send + fmt::Debug { // Debug: family & parameters, e.g. in tiled render manifests
+	fn z0(&self, p : ComplexF32) -> ComplexF32 { p }
+	fn c(&self, p : ComplexF32) -> ComplexF32 { p }  // parameter plane by default, julia like families return a constant
+	fn step(&self, z : ComplexF32, z_prev : ComplexF32, c : ComplexF32) -> ComplexF32;
+	fn bailout(&self) -> f32 { 2. }
+	fn degree(&self) -> f32 { 2. } // growth rate of |z| near infinity, for smooth coloring
+
+	// iterate until |z| > bailout
+	fn escape(&self, p : ComplexF32, iters : u32, bailout : f32) -> Escape {
+		let c = self.c(p);
+		let (mut z, mut z_prev) = (self.z0(p), ComplexF32::new(0., 0.));
+
+		let mut ix : u32 = 0;
+		while ix < iters {
+			let z_next = self.step(z, z_prev, c);
+			z_prev = z;
+			z = z_next;
+			if z.norm() > bailout { break }
+
+			ix += 1;
+		}
+		Escape { iters : ix, z }
+	}
+
+	fn interior(&self, _p : ComplexF32) -> bool { false } // p is known not to escape
+
+	// escape with Brent's cycle detection, an orbit that repeats exactly never escapes, same result as escape
+	fn escape_periodic(&self, p : ComplexF32, iters : u32, bailout : f32) -> Escape {
+		if self.interior(p) { return Escape { iters, z : p } }
+
+		let c = self.c(p);
+		let (mut z, mut z_prev) = (self.z0(p), ComplexF32::new(0., 0.));
+		let (mut saved, mut saved_prev, mut steps, mut power) = (z, z_prev, 0, 1);
+
+		let mut ix : u32 = 0;
+		while ix < iters {
+			let z_next = self.step(z, z_prev, c);
+			z_prev = z;
+			z = z_next;
+			if z.norm() > bailout { break }
+
+			ix += 1;
+			if z == saved && z_prev == saved_prev { return Escape { iters, z } }
+			steps += 1;
+			if steps == power { // cycle length candidates double
+				saved = z;
+				saved_prev = z_prev;
+				steps = 0;
+				power *= 2;
+			}
+		}
+		Escape { iters : ix, z }
+	}
+
+	// d z(n+1) / dp from z(n) and d z(n) / dp, None: not holomorphic, no distance estimation
+	fn d_step(&self, _z : ComplexF32, _dz : ComplexF32) -> Option<ComplexF32> { None }
+
+	// escape tracking the derivative dz/dp, from z0 = p: dz0 = 1
+	fn escape_d(&self, p : ComplexF32, iters : u32, bailout : f32) -> Option<(Escape, ComplexF32)> {
+		let c = self.c(p);
+		let (mut z, mut z_prev, mut dz) = (self.z0(p), ComplexF32::new(0., 0.), ComplexF32::new(1., 0.));
+
+		let mut ix : u32 = 0;
+		while ix < iters {
+			dz = self.d_step(z, dz)?;
+			let z_next = self.step(z, z_prev, c);
+			z_prev = z;
+			z = z_next;
+			if z.norm() > bailout { break }
+
+			ix += 1;
+		}
+		Some((Escape { iters : ix, z }, dz))
+	}
+
+	// exterior distance estimate to the set, |z| ln|z| / |dz|
+	fn distance(&self, e : &Escape, dz : ComplexF32) -> f32 {
+		let r = e.z.norm();
+		r * r.ln() / dz.norm()
+	}
+
+	// normalized iteration count, continuous across iteration bands
+	fn smooth(&self, e : &Escape, bailout : f32) -> f32 {
+		let d = self.degree();
+		if d <= 1. { return e.iters as f32 }
+		(e.iters as f32 + 1. - (e.z.norm().ln() / bailout.ln()).ln() / d.ln()).max(0.)
+	}
+}
+
+// z*z + c, the typical 2nd order fractal
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MandelbrotSet;
+
+impl EscapeTime for MandelbrotSet {
+	fn step(&self, z : ComplexF32, _ : ComplexF32, c : ComplexF32) -> ComplexF32 { z * z + c }
+	fn d_step(&self, z : ComplexF32, dz : ComplexF32) -> Option<ComplexF32> { Some(2. * z * dz + 1.) }
+
+	fn interior(&self, c : ComplexF32) -> bool { // main cardioid or period 2 bulb
+		let (x, y2) = (c.re - 0.25, c.im * c.im);
+		let q = x * x + y2;
+		q * (q + x) < 0.25 * y2 || (c.re + 1.) * (c.re + 1.) + y2 < 0.0625
+	}
+}
+
+// z*z + c, c fixed, pixel is z0
+#[derive(Clone, Copy, Debug)]
+pub struct Julia { pub c : ComplexF32 }
+
+impl EscapeTime for Julia {
+	fn c(&self, _ : ComplexF32) -> ComplexF32 { self.c }
+	fn step(&self, z : ComplexF32, _ : ComplexF32, c : ComplexF32) -> ComplexF32 { z * z + c }
+	fn d_step(&self, z : ComplexF32, dz : ComplexF32) -> Option<ComplexF32> { Some(2. * z * dz) }
+}
+
+// z^n + c, any real n
+#[derive(Clone, Copy, Debug)]
+pub struct Multibrot { pub n : f32 }
+
+impl EscapeTime for Multibrot {
+	fn degree(&self) -> f32 { self.n.abs() }
+	fn d_step(&self, z : ComplexF32, dz : ComplexF32) -> Option<ComplexF32> { Some(self.n * z.powf(self.n - 1.) * dz + 1.) }
+	fn step(&self, z : ComplexF32, _ : ComplexF32, c : ComplexF32) -> ComplexF32 {
+		if self.n.fract() == 0. { z.powi(self.n as i32) + c } else { z.powf(self.n) + c }
+	}
+}
+
+// (|re| + i|im|)^2 + c
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BurningShip;
+
+impl EscapeTime for BurningShip {
+	fn step(&self, z : ComplexF32, _ : ComplexF32, c : ComplexF32) -> ComplexF32 {
+		let z = ComplexF32::new(z.re.abs(), z.im.abs());
+		z * z + c
+	}
+}
+
+// mandelbar: conj(z)^2 + c
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tricorn;
+
+impl EscapeTime for Tricorn {
+	fn step(&self, z : ComplexF32, _ : ComplexF32, c : ComplexF32) -> ComplexF32 {
+		let z = z.conj();
+		z * z + c
+	}
+}
+
+// z*z + c + p*z(n-1), c & p fixed, pixel is z0
+#[derive(Clone, Copy, Debug)]
+pub struct Phoenix { pub c : ComplexF32, pub p : ComplexF32 }
+
+impl Default for Phoenix {
+	fn default() -> Self { Self { c : ComplexF32::new(0.5667, 0.), p : ComplexF32::new(-0.5, 0.) } }
+}
+
+impl EscapeTime for Phoenix {
+	fn c(&self, _ : ComplexF32) -> ComplexF32 { self.c }
+	fn step(&self, z : ComplexF32, z_prev : ComplexF32, c : ComplexF32) -> ComplexF32 { z * z + c + self.p * z_prev }
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn families() {
+		let (inside, outside) = (ComplexF32::new(-0.1, 0.1), ComplexF32::new(1., 1.));
+		for f in [&MandelbrotSet as &dyn EscapeTime, &Multibrot { n : 2. }, &Multibrot { n : 2.5 }, &BurningShip, &Tricorn].iter() {
+			assert_eq!(f.escape(inside, 100, 2.).iters, 100);
+			assert!(f.escape(outside, 100, 2.).iters < 3);
+		}
+
+		let j = Julia { c : ComplexF32::new(0., 0.) }; // unit disk
+		assert_eq!(j.escape(ComplexF32::new(0.9, 0.), 100, 2.).iters, 100);
+		assert!(j.escape(ComplexF32::new(1.1, 0.), 100, 2.).iters < 100);
+
+		let ph = Phoenix { c : ComplexF32::new(0., 0.), p : ComplexF32::new(0., 0.) }; // p=0 is julia
+		for &p in [0.5_f32, 0.99, 1.01, 1.5].iter() {
+			let p = ComplexF32::new(p, 0.3);
+			assert_eq!(ph.escape(p, 50, 2.), j.escape(p, 50, 2.));
+		}
+	}
+
+	#[test]
+	fn interior() {
+		let m = MandelbrotSet;
+		for &(re, im) in [(0., 0.), (-0.1, 0.5), (0.2, 0.), (-1., 0.), (-1.2, 0.1)].iter() { assert!(m.interior(ComplexF32::new(re, im))) }
+		for &(re, im) in [(0.3, 0.), (-0.75, 0.1), (-1.3, 0.), (-0.5, 0.7), (0.26, 0.)].iter() { assert!(!m.interior(ComplexF32::new(re, im))) }
+
+		for f in [&m as &dyn EscapeTime, &Julia { c : ComplexF32::new(-0.8, 0.156) }, &Phoenix::default(), &Multibrot { n : 3. }].iter() {
+			for i in 0..400 {
+				let p = ComplexF32::new(-2. + (i % 20) as f32 * 0.2, -1.5 + (i / 20) as f32 * 0.15);
+				assert_eq!(f.escape_periodic(p, 500, 2.).iters, f.escape(p, 500, 2.).iters, "{:?}", p);
+			}
+		}
+	}
+
+	#[test]
+	fn distance() { // estimate within a factor 4 of the true distance
+		let m = MandelbrotSet;
+		for &(re, im, d) in [(0.5_f32, 0., 0.25_f32), (1., 0., 0.75), (-2.5, 0., 0.5), (0., 1.5, 0.5)].iter() {
+			let (e, dz) = m.escape_d(ComplexF32::new(re, im), 1000, 1e4).unwrap();
+			let de = m.distance(&e, dz);
+			assert!(de > d / 4. && de < d * 4., "{} {}: {} vs {}", re, im, de, d);
+		}
+		let j = Julia { c : ComplexF32::new(0., 0.) }; // unit circle
+		let (e, dz) = j.escape_d(ComplexF32::new(1.5, 0.), 1000, 1e4).unwrap();
+		assert!((j.distance(&e, dz) - 0.5).abs() < 0.5);
+		assert!(m.escape_d(ComplexF32::new(-0.1, 0.), 100, 2.).unwrap().0.iters == 100);
+		assert!(BurningShip.escape_d(ComplexF32::new(1., 1.), 100, 2.).is_none());
+	}
+
+	#[test]
+	fn smooth() { // continuous along a ray crossing iteration bands
+		let (f, bailout) = (MandelbrotSet, 256.);
+		let nu = |x : f32| f.smooth(&f.escape(ComplexF32::new(x, 0.), 100, bailout), bailout);
+		let (mut prev, mut bands) = (nu(0.3), 0);
+		for i in 1..2000 {
+			let e = f.escape(ComplexF32::new(0.3 + i as f32 * 1e-4, 0.), 100, bailout);
+			let v = nu(0.3 + i as f32 * 1e-4);
+			assert!((v - prev).abs() < 0.1, "jump at {}: {} {}", i, prev, v);
+			if e.iters != f.escape(ComplexF32::new(0.3 + (i - 1) as f32 * 1e-4, 0.), 100, bailout).iters { bands += 1 }
+			prev = v;
+		}
+		assert!(bands > 3);
+	}
+}
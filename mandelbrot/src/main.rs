@@ -1,21 +1,180 @@
+#![allow(dead_code)]
 
+
+mod fractal;
+mod palette;
+mod perturbation;
+mod viewport;
+mod buddhabrot;
+mod distance;
+mod tiled;
 mod mandelbrot;
 use mandelbrot::*;
 use std::time::Instant;
+use num::complex::Complex64;
 
 fn main() {
     let mf = 2;
     let (w, h, iters) = (mf*1024, mf*1024, 200);
 
-    println!("Generating mandelbrot {} x {} = {} pix, {} iters...", w, h, w*h, iters);
+    let mnd = Mandelbrot::new(w, h, Viewport::default(), iters);
+    let mut zoom = None;
+    let mut buddhabrot : Option<Buddhabrot> = None;
+    let (mut heightmap, mut obj) = (None, None);
+    let mut tiled = None;
+
+    // mandelbrot [family] [--smooth] [--equalize] [--palette file.map|stops.txt] [--size WxH] [--iters n] [--interior]
+    //            [--view re,im,width[,degrees]] [--deep re im width] [--zoom frames end_width iter_growth]
+    //            [--buddhabrot limit samples | --nebulabrot r,g,b samples] [--anti] [--metropolis] [--gamma g]
+    //            [--shading distance|slope] [--heightmap distance|smooth] [--obj distance|smooth relief_mm step]
+    //            [--tiled strip_rows]   (0: auto, resumes an interrupted render from mandel.png.strips)
+    let mut args = std::env::args().skip(1).peekable();
+    let family = if args.peek().is_some_and(|a| !a.starts_with("--")) { args.next() } else { None };
+
+    let mut mnd = match family.as_deref() { // fractal family
+        None | Some("mandelbrot") => mnd,
+        Some("julia")       => mnd.with_fractal(Julia { c : ComplexF32::new(-0.8, 0.156) }),
+        Some("multibrot")   => mnd.with_fractal(Multibrot { n : 3.5 }),
+        Some("burningship") => mnd.with_fractal(BurningShip),
+        Some("tricorn")     => mnd.with_fractal(Tricorn),
+        Some("phoenix")     => mnd.with_fractal(Phoenix::default()),
+        Some(name) => { eprintln!("unknown fractal '{}': mandelbrot julia multibrot burningship tricorn phoenix", name); std::process::exit(2) }
+    };
+
+    while let Some(arg) = args.next() {
+        mnd = match arg.as_str() {
+            "--smooth"   => mnd.with_smooth(true),
+            "--equalize" => mnd.with_equalize(true),
+            "--interior" => mnd.with_interior(true),
+            "--palette"  => match args.next().map(|name| Palette::load(&name)) {
+                Some(Ok(palette)) => mnd.with_palette(palette),
+                Some(Err(err))    => { eprintln!("{}", err); std::process::exit(1) }
+                None              => { eprintln!("missing palette file"); std::process::exit(2) }
+            },
+            "--size"     => match args.next().as_deref().and_then(|s| s.split_once('x')).map(|(w, h)| (w.parse::<u32>(), h.parse::<u32>())) {
+                Some((Ok(w), Ok(h))) if w > 0 && h > 0 => Mandelbrot { w, h, ..mnd },
+                _                    => { eprintln!("bad size, expected WxH, both above 0"); std::process::exit(2) }
+            },
+            "--iters"    => match args.next().map(|n| n.parse::<u32>()) {
+                Some(Ok(iters)) => Mandelbrot { iters, ..mnd },
+                _               => { eprintln!("bad iteration count"); std::process::exit(2) }
+            },
+            "--view"     => match args.next().map(|v| v.split(',').map(|x| x.parse::<f64>()).collect::<Result<Vec<_>, _>>()) {
+                Some(Ok(v)) if v.len() == 3 || v.len() == 4 => 
+                    Mandelbrot { viewport : Viewport::new(Complex64::new(v[0], v[1]), v[2]).with_rotation(v.get(3).map_or(0., |d| d.to_radians())), ..mnd },
+                _ => { eprintln!("bad view, expected re,im,width[,degrees]"); std::process::exit(2) }
+            },
+            "--deep"     => match (args.next(), args.next(), args.next().and_then(|w| w.parse::<f64>().ok())) {
+                (Some(re), Some(im), Some(width)) => match DeepZoom::new(&re, &im, width) {
+                    Ok(deep) => Mandelbrot { viewport : Viewport { center : deep.center_c64(), width, ..mnd.viewport }, ..mnd }.with_deep_zoom(deep),
+                    Err(err) => { eprintln!("{}", err); std::process::exit(2) }
+                },
+                _ => { eprintln!("expected --deep re im width"); std::process::exit(2) }
+            },
+            "--zoom"     => match (args.next().map(|n| n.parse::<u32>()), args.next().map(|w| w.parse::<f64>()), args.next().map(|g| g.parse::<f64>())) {
+                (Some(Ok(frames)), Some(Ok(end_width)), Some(Ok(iter_growth))) => { zoom = Some(ZoomAnimation { frames, end_width, iter_growth }); mnd }
+                _ => { eprintln!("expected --zoom frames end_width iter_growth"); std::process::exit(2) }
+            },
+            "--buddhabrot" | "--nebulabrot" => {
+                let limits = args.next().map(|l| l.split(',').map(|x| x.parse::<u32>()).collect::<Result<Vec<_>, _>>());
+                match (limits, args.next().map(|n| n.parse::<u64>())) {
+                    (Some(Ok(l)), Some(Ok(samples))) if l.len() == 1 || l.len() == 3 => {
+                        let b = if l.len() == 1 { Buddhabrot::new(l[0]) } else { Buddhabrot::nebulabrot([l[0], l[1], l[2]]) };
+                        buddhabrot = Some(b.with_samples(samples));
+                        mnd
+                    }
+                    _ => { eprintln!("expected {} limit[,limit,limit] samples", arg); std::process::exit(2) }
+                }
+            },
+            "--anti" | "--metropolis" | "--gamma" => match buddhabrot {
+                Some(b) => {
+                    buddhabrot = Some(match arg.as_str() {
+                        "--anti"       => b.with_anti(true),
+                        "--metropolis" => b.with_metropolis(true),
+                        _ => match args.next().map(|g| g.parse::<f32>()) {
+                            Some(Ok(g)) => b.with_tone(ToneMap::Gamma(g)),
+                            _           => { eprintln!("bad gamma"); std::process::exit(2) }
+                        }
+                    });
+                    mnd
+                }
+                None => { eprintln!("{} needs --buddhabrot or --nebulabrot first", arg); std::process::exit(2) }
+            },
+            "--tiled"    => match args.next().map(|n| n.parse::<u32>()) {
+                Some(Ok(rows)) => { tiled = Some(TiledRender::default().with_strip_rows(rows)); mnd }
+                _              => { eprintln!("expected --tiled strip_rows"); std::process::exit(2) }
+            },
+            "--shading"  => match args.next().as_deref() {
+                Some("distance") => mnd.with_shading(Shading::Distance),
+                Some("slope")    => mnd.with_shading(Shading::Slope),
+                Some("palette")  => mnd.with_shading(Shading::Palette),
+                _                => { eprintln!("expected --shading palette|distance|slope"); std::process::exit(2) }
+            },
+            "--heightmap" | "--obj" => {
+                let kind = match args.next().as_deref() {
+                    Some("distance") => HeightField::Distance,
+                    Some("smooth")   => HeightField::Smooth,
+                    _                => { eprintln!("expected {} distance|smooth", arg); std::process::exit(2) }
+                };
+                if arg == "--heightmap" { heightmap = Some(kind) } else {
+                    match (args.next().map(|r| r.parse::<f32>()), args.next().map(|s| s.parse::<u32>())) {
+                        (Some(Ok(relief)), Some(Ok(step))) => obj = Some((kind, relief, step)),
+                        _ => { eprintln!("expected --obj distance|smooth relief_mm step"); std::process::exit(2) }
+                    }
+                }
+                mnd
+            },
+            _ => { eprintln!("unknown option '{}'", arg); std::process::exit(2) }
+        }
+    }
+
+    if mnd.deep.is_some() && (!matches!(family.as_deref(), None | Some("mandelbrot")) || mnd.interior) {
+        eprintln!("--deep renders mandelbrot only, without --interior");
+        std::process::exit(2)
+    }
 
-    let mut mnd = Mandelbrot::new(w, h, ComplexF32::new(0.5, 0.0), ComplexF32::new(-2.0, 2.0), iters);
+    if tiled.is_some() && (buddhabrot.is_some() || heightmap.is_some() || obj.is_some()) {
+        eprintln!("--tiled renders escape time images only, without --buddhabrot, --nebulabrot, --heightmap or --obj");
+        std::process::exit(2)
+    }
+
+    let needs_distance = mnd.shading != Shading::Palette || heightmap == Some(HeightField::Distance) || obj.is_some_and(|o| o.0 == HeightField::Distance);
+    if needs_distance && !mnd.has_distance() {
+        eprintln!("distance estimation needs mandelbrot, julia or multibrot without --deep");
+        std::process::exit(2)
+    }
+
+    println!("Generating mandelbrot {} x {} = {} pix, {} iters...", mnd.w, mnd.h, mnd.w*mnd.h, mnd.iters);
 
     let t = Instant::now();
 
-    mnd.generate();
+    if let Some(anim) = zoom {
+        let names = mnd.zoom_animation(&anim, "mandel");
+        println!("lap: {:?}, {} frames {}..", Instant::now() - t, names.len(), names.first().map_or("", |n| n.as_str()));
+        return
+    }
+
+    if let Some(strips) = tiled {
+        match mnd.render_tiled("mandel.png", &strips) {
+            Ok(())   => println!("lap: {:?}", Instant::now() - t),
+            Err(err) => { eprintln!("{}", err); std::process::exit(1) }
+        }
+        return
+    }
+
+    match &buddhabrot {
+        Some(b) => mnd.generate_buddhabrot(b),
+        None    => mnd.generate(),
+    }
 
     println!("lap: {:?}", Instant::now() - t);
 
     mnd.write_png("mandel.png");
+
+    if let Some(kind) = heightmap {
+        if let Err(err) = mnd.write_heightmap("mandel_height.png", kind) { eprintln!("{}", err); std::process::exit(1) }
+    }
+    if let Some((kind, relief, step)) = obj {
+        if let Err(err) = mnd.write_obj("mandel.obj", kind, relief, step) { eprintln!("{}", err); std::process::exit(1) }
+    }
 }
@@ -2,80 +2,163 @@
 	mandelbrot fractal
 */
 
-use num::complex::*;
 use rayon::prelude::*;
 use std::convert::TryInto;
-use image::{ImageBuffer, Rgb, Rgba};
-
-pub type ComplexF32 = Complex<f32>;
-
-const FIRE_PALETTE : [u32; 256] = [0, 0, 4, 12, 16, 24, 32, 36, 44, 48, 56, 64, 68, 76, 80, 88, 96, 100, 108, 116, 120, 128, 132,
-	140, 148, 152, 160, 164, 172, 180, 184, 192, 200, 1224, 3272, 4300, 6348, 7376, 9424, 10448,
-	12500, 14548, 15576, 17624, 18648, 20700, 21724, 23776, 25824, 26848, 28900, 29924, 31976,
-	33000, 35048, 36076, 38124, 40176, 41200, 43248, 44276, 46324, 47352, 49400, 51452, 313596,
-	837884, 1363196, 1887484, 2412796, 2937084, 3461372, 3986684, 4510972, 5036284, 5560572,
-	6084860, 6610172, 7134460, 7659772, 8184060, 8708348, 9233660, 9757948, 10283260, 10807548,
-	11331836, 11857148, 12381436, 12906748, 13431036, 13955324, 14480636, 15004924, 15530236,
-	16054524, 16579836, 16317692, 16055548, 15793404, 15269116, 15006972, 14744828, 14220540,
-	13958396, 13696252, 13171964, 12909820, 12647676, 12123388, 11861244, 11599100, 11074812,
-	10812668, 10550524, 10288380, 9764092, 9501948, 9239804, 8715516, 8453372, 8191228, 7666940,
-	7404796, 7142652, 6618364, 6356220, 6094076, 5569788, 5307644, 5045500, 4783356, 4259068,
-	3996924, 3734780, 3210492, 2948348, 2686204, 2161916, 1899772, 1637628, 1113340, 851196,
-	589052, 64764, 63740, 62716, 61692, 59644, 58620, 57596, 55548, 54524, 53500, 51452, 50428,
-	49404, 47356, 46332, 45308, 43260, 42236, 41212, 40188, 38140, 37116, 36092, 34044, 33020,
-	31996, 29948, 28924, 27900, 25852, 24828, 23804, 21756, 20732, 19708, 18684, 16636, 15612,
-	14588, 12540, 11516, 10492, 8444, 7420, 6396, 4348, 3324, 2300, 252, 248, 244, 240, 236, 232,
-	228, 224, 220, 216, 212, 208, 204, 200, 196, 192, 188, 184, 180, 176, 172, 168, 164, 160, 156,
-	152, 148, 144, 140, 136, 132, 128, 124, 120, 116, 112, 108, 104, 100, 96, 92, 88, 84, 80, 76,
-	72, 68, 64, 60, 56, 52, 48, 44, 40, 36, 32, 28, 24, 20, 16, 12, 8, 0, 0];
+use std::sync::Arc;
+use image::{ImageBuffer, Rgb};
+use num::complex::Complex64;
 
+pub use crate::fractal::*;
+pub use crate::palette::*;
+pub use crate::perturbation::DeepZoom;
+pub use crate::viewport::*;
+pub use crate::buddhabrot::*;
+pub use crate::distance::*;
+pub use crate::tiled::TiledRender;
+
+pub(crate) const SMOOTH_BAILOUT : f32 = 256.; // large bailout for smooth coloring, bands blend without seams
+const PALETTE_CYCLE : u32 = 50; // iterations per palette cycle
 
 pub struct Mandelbrot {
 	pub w : u32,
 	pub h : u32,
 	pub iters : u32,
-	pub center: ComplexF32,
-	pub range : ComplexF32,
+	pub viewport : Viewport,
 	pub image : Vec<u32>,
+	pub fractal : Arc<dyn EscapeTime>,
+	pub palette : Palette,
+	pub cycle 	: u32,	// iterations per palette cycle
+	pub smooth 	: bool, // normalized iteration count, no banding
+	pub equalize : bool, // histogram equalization, palette spread once over the escaped pixels
+	pub interior : bool, // cardioid & bulb tests, periodicity checking
+	pub deep 	: Option<DeepZoom>, // perturbation render of z*z + c, its center replaces the viewport's
+	pub frame 	: Option<Frame>, // window of a larger image, the viewport is the whole image's
+	pub shading : Shading, // distance & slope need a family with derivative, else palette
 }
 
 
 impl Mandelbrot {
-	pub fn new( w : u32, h: u32, center : ComplexF32, range : ComplexF32, iters : u32) -> Self {
+	pub fn new( w : u32, h: u32, viewport : Viewport, iters : u32) -> Self {
 		
-		Self{w, h, iters, center, range, image: vec![]}
+		Self{w, h, iters, viewport, image: vec![], fractal : Arc::new(MandelbrotSet),
+			palette : Palette::default(), cycle : PALETTE_CYCLE, smooth : false, equalize : false,
+			interior : false, deep : None, frame : None, shading : Shading::Palette}
 	}
 
-	pub fn generate(&mut self)  {
+	pub fn with_fractal(mut self, fractal : impl EscapeTime + 'static) -> Self {
+		self.fractal = Arc::new(fractal);
+		self
+	}
+
+	pub fn with_palette(mut self, palette : Palette) -> Self {
+		self.palette = palette;
+		self
+	}
+
+	pub fn with_cycle(mut self, cycle : u32) -> Self {
+		self.cycle = cycle.max(1);
+		self
+	}
+
+	pub fn with_smooth(mut self, smooth : bool) -> Self {
+		self.smooth = smooth;
+		self
+	}
+
+	pub fn with_equalize(mut self, equalize : bool) -> Self {
+		self.equalize = equalize;
+		self
+	}
+
+	pub fn with_interior(mut self, interior : bool) -> Self {
+		self.interior = interior;
+		self
+	}
+
+	pub fn with_deep_zoom(mut self, deep : DeepZoom) -> Self {
+		self.deep = Some(deep);
+		self
+	}
+
+	// same render restricted to the ww x wh window at (x0, y0), without image
+	// its pixels map through the whole image, a window gives the very same pixels
+	pub fn window(&self, x0 : u32, y0 : u32, ww : u32, wh : u32) -> Self {
+		let frame = self.whole_frame();
+		let frame = Frame { x0 : frame.x0 + x0, y0 : frame.y0 + y0, ..frame };
+		Self { w : ww, h : wh, image : vec![], fractal : self.fractal.clone(), palette : self.palette.clone(), deep : self.deep.clone(), frame : Some(frame), ..*self }
+	}
+
+	pub fn whole_frame(&self) -> Frame { self.frame.unwrap_or(Frame { x0 : 0, y0 : 0, w : self.w, h : self.h }) }
+
+	// pixel center relative to the view center
+	pub fn pixel_offset(&self, i : u32, j : u32) -> Complex64 {
+		let frame = self.whole_frame();
+		self.viewport.offset(frame.x0 + i, frame.y0 + j, frame.w, frame.h)
+	}
+
+	pub fn pixel_to_c(&self, i : u32, j : u32) -> Complex64 { self.viewport.center + self.pixel_offset(i, j) }
+
+	// iteration count per pixel, smooth if set, None: doesn't escape
+	pub fn escape_counts(&self) -> Vec<Option<f32>> {
 		
-		fn do_scale(cr : ComplexF32, range : ComplexF32, i  : u32, j : u32, w : u32, h : u32) -> ComplexF32 {
-            cr + ComplexF32::new((range.im - range.re) * i as f32 / w as f32,
-							  (range.im - range.re) * j as f32 / h as f32)
+		let bailout = if self.smooth { self.fractal.bailout().max(SMOOTH_BAILOUT) } else { self.fractal.bailout() };
+
+		if let Some(deep) = &self.deep {
+			return deep.escape_all(self.w, self.h, |i, j| self.pixel_offset(i, j), self.iters, bailout as f64).into_par_iter().map(|(iters, z)|
+				if iters >= self.iters { None } 
+				else if self.smooth { Some(MandelbrotSet.smooth(&Escape { iters, z : ComplexF32::new(z.re as f32, z.im as f32) }, bailout)) } 
+				else { Some(iters as f32) }
+			).collect()
 		}
+
+		(0..self.w * self.h).into_par_iter().map(|index| self.pixel_count(index % self.w, index / self.w, bailout)).collect()
+	}
+
+	pub fn pixel_count(&self, i : u32, j : u32, bailout : f32) -> Option<f32> {
+		let c = self.pixel_to_c(i, j);
+		let c0 = ComplexF32::new(c.re as f32, c.im as f32);
+		let e = if self.interior { self.fractal.escape_periodic(c0, self.iters, bailout) } else { self.fractal.escape(c0, self.iters, bailout) };
 		
-		let pal_len = FIRE_PALETTE.len();
-		let scale = 0.8_f32;
-		let ratio = self.w as f32 / self.h as f32;
-		let cr = ComplexF32::new(self.range.re, self.range.re);
-		
-		self.image = (0..self.w * self.h).into_par_iter().map (
-			|index| {
-				let (i, j) = (index % self.w,  index / self.w);
-				
-				let c0 = (scale * ratio) * do_scale(cr, self.range, i, j, self.w, self.h) - self.center;
-				let mut z = c0;
-
-				let mut ix : u32 = 0;
-				while ix < self.iters {
-					z = z * z + c0;  // z*z is the typical 2nd order fractal
-					if z.norm() > 2.0 { break }
-
-					ix+=1;
-				}
-				
-				if ix >= self.iters { 0 } else { FIRE_PALETTE[(pal_len * ix as usize / 50) % pal_len] }
+		if e.iters >= self.iters { None } 
+		else if self.smooth { Some(self.fractal.smooth(&e, bailout)) } 
+		else { Some(e.iters as f32) }
+	}
+
+	// cumulative distribution of escaped pixels over iteration counts, cdf[i] : fraction below i
+	pub fn histogram_cdf(&self, counts : &[Option<f32>]) -> Vec<f32> {
+		let mut hist = vec![0_usize; self.iters as usize + 2];
+		for v in counts.iter().flatten() { hist[(*v as usize).min(self.iters as usize)] += 1 }
+
+		let total = hist.iter().sum::<usize>().max(1) as f32;
+		let mut acc = 0;
+		let mut cdf = Vec::with_capacity(hist.len());
+		for n in hist {
+			cdf.push(acc as f32 / total);
+			acc += n;
+		}
+		cdf
+	}
+
+	pub fn colorize(&self, counts : &[Option<f32>]) -> Vec<u32> {
+		let pal_len = self.palette.len();
+		let cdf = if self.equalize { self.histogram_cdf(counts) } else { vec![] };
+
+		counts.par_iter().map(|v| match *v {
+			None => 0,
+			Some(v) if self.equalize => {
+				let i = (v as usize).min(cdf.len() - 2);
+				let t = cdf[i] + (cdf[i + 1] - cdf[i]) * if self.smooth { v.fract() } else { 0. };
+				self.palette.lerp(t * (pal_len - 1) as f32)
 			}
-		).collect();
+			Some(v) if self.smooth => self.palette.lerp(v * pal_len as f32 / self.cycle as f32),
+			Some(v) => self.palette.colors[(pal_len * v as usize / self.cycle as usize) % pal_len],
+		}).collect()
+	}
+
+	pub fn generate(&mut self)  {
+		if self.shading != Shading::Palette { self.image = self.shade(); return }
+		let counts = self.escape_counts();
+		self.image = self.colorize(&counts);
 	}
 
 	pub fn get_pixel_rgb(&self, index : usize) -> [u8; 3] {
@@ -90,4 +173,57 @@ impl Mandelbrot {
 		}
 		imgbuf.save(name).unwrap();
 	}
-}
\ No newline at end of file
+
+	// renders the zoom into the view center as prefix_00000.png.., returns the file names
+	// deep zooms need the DeepZoom precision for anim.end_width
+	pub fn zoom_animation(&mut self, anim : &ZoomAnimation, prefix : &str) -> Vec<String> {
+		let (viewport, iters) = (self.viewport, self.iters);
+
+		let names = (0..anim.frames).map(|k| {
+			let (vp, it) = anim.frame(&viewport, iters, k);
+			self.viewport = vp;
+			self.iters = it;
+			self.generate();
+
+			let name = format!("{}_{:05}.png", prefix, k);
+			self.write_png(&name);
+			name
+		}).collect();
+
+		self.viewport = viewport;
+		self.iters = iters;
+		names
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use num::complex::Complex64;
+	use std::time::Instant;
+
+	// same image, brute force vs cardioid/bulb tests & periodicity checking
+	fn compare(w : u32, h : u32, iters : u32, timed : bool) {
+		let views = [Viewport::default(), Viewport::new(Complex64::new(-0.7436, 0.1318), 0.01), Viewport::new(Complex64::new(-0.16, 1.035), 0.05)];
+		for vp in views.iter() {
+			let mnd = Mandelbrot::new(w, h, *vp, iters);
+			let t = Instant::now();
+			let brute = mnd.escape_counts();
+			let lap = Instant::now() - t;
+
+			let t = Instant::now();
+			let counts = mnd.window(0, 0, w, h).with_interior(true).escape_counts();
+			let lap_interior = Instant::now() - t;
+			let diff = counts.iter().zip(brute.iter()).filter(|(a, b)| a != b).count();
+			if timed { println!("{:?}\n\tbrute force: {:?}, interior: {:?}, {} pixels differ", vp, lap, lap_interior, diff) }
+			assert_eq!(diff, 0);
+		}
+	}
+
+	#[test]
+	fn interior() { compare(160, 120, 300, false) }
+
+	#[test]
+	#[ignore] // cargo test --release bench_interior -- --ignored --nocapture
+	fn bench_interior() { compare(320, 240, 1000, true) }
+}
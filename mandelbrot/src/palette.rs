@@ -0,0 +1,148 @@
+/*
+	color palettes, pixel layout is get_pixel_rgb's: r | g<<8 | b<<16
+
+	loadable formats:
+		.map 	fractint map, one 'r g b' (0..255) per line, anything after the 3rd value ignored
+		other 	gradient stops, one 'position color' per line, position 0..1, color '#rrggbb' or 'r g b'
+	blank lines & # comments are skipped
+*/
+
+use std::io;
+
+pub const FIRE_PALETTE : [u32; 256] = [0, 0, 4, 12, 16, 24, 32, 36, 44, 48, 56, 64, 68, 76, 80, 88, 96, 100, 108, 116, 120, 128, 132,
+	140, 148, 152, 160, 164, 172, 180, 184, 192, 200, 1224, 3272, 4300, 6348, 7376, 9424, 10448,
+	12500, 14548, 15576, 17624, 18648, 20700, 21724, 23776, 25824, 26848, 28900, 29924, 31976,
+	33000, 35048, 36076, 38124, 40176, 41200, 43248, 44276, 46324, 47352, 49400, 51452, 313596,
+	837884, 1363196, 1887484, 2412796, 2937084, 3461372, 3986684, 4510972, 5036284, 5560572,
+	6084860, 6610172, 7134460, 7659772, 8184060, 8708348, 9233660, 9757948, 10283260, 10807548,
+	11331836, 11857148, 12381436, 12906748, 13431036, 13955324, 14480636, 15004924, 15530236,
+	16054524, 16579836, 16317692, 16055548, 15793404, 15269116, 15006972, 14744828, 14220540,
+	13958396, 13696252, 13171964, 12909820, 12647676, 12123388, 11861244, 11599100, 11074812,
+	10812668, 10550524, 10288380, 9764092, 9501948, 9239804, 8715516, 8453372, 8191228, 7666940,
+	7404796, 7142652, 6618364, 6356220, 6094076, 5569788, 5307644, 5045500, 4783356, 4259068,
+	3996924, 3734780, 3210492, 2948348, 2686204, 2161916, 1899772, 1637628, 1113340, 851196,
+	589052, 64764, 63740, 62716, 61692, 59644, 58620, 57596, 55548, 54524, 53500, 51452, 50428,
+	49404, 47356, 46332, 45308, 43260, 42236, 41212, 40188, 38140, 37116, 36092, 34044, 33020,
+	31996, 29948, 28924, 27900, 25852, 24828, 23804, 21756, 20732, 19708, 18684, 16636, 15612,
+	14588, 12540, 11516, 10492, 8444, 7420, 6396, 4348, 3324, 2300, 252, 248, 244, 240, 236, 232,
+	228, 224, 220, 216, 212, 208, 204, 200, 196, 192, 188, 184, 180, 176, 172, 168, 164, 160, 156,
+	152, 148, 144, 140, 136, 132, 128, 124, 120, 116, 112, 108, 104, 100, 96, 92, 88, 84, 80, 76,
+	72, 68, 64, 60, 56, 52, 48, 44, 40, 36, 32, 28, 24, 20, 16, 12, 8, 0, 0];
+
+const GRADIENT_SIZE : usize = 256; // entries sampled from gradient stops
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+	pub colors : Vec<u32>,
+}
+
+impl Default for Palette {
+	fn default() -> Self { Self::fire() }
+}
+
+pub fn rgb(c : [u8; 3]) -> u32 { u32::from_ne_bytes([c[0], c[1], c[2], 0]) }
+
+pub fn rgb_components(c : u32) -> [f32; 3] {
+	let b = c.to_ne_bytes();
+	[b[0] as f32, b[1] as f32, b[2] as f32]
+}
+
+fn invalid(name : &str, line : usize, msg : &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: {}", name, line + 1, msg))
+}
+
+fn parse_rgb(s : &[&str]) -> Option<[u8; 3]> {
+	match s {
+		[hex] if hex.len() == 7 && hex.starts_with('#') => {
+			let v = u32::from_str_radix(&hex[1..], 16).ok()?;
+			Some([(v >> 16) as u8, (v >> 8) as u8, v as u8])
+		}
+		[r, g, b, ..] => Some([r.parse().ok()?, g.parse().ok()?, b.parse().ok()?]),
+		_ => None,
+	}
+}
+
+fn lines(text : &str) -> impl Iterator<Item = (usize, Vec<&str>)> {
+	text.lines().enumerate()
+		.map(|(n, l)| (n, l.trim()))
+		.filter(|(_, l)| !l.is_empty() && !l.starts_with('#'))
+		.map(|(n, l)| (n, l.split_whitespace().collect()))
+}
+
+impl Palette {
+	pub fn fire() -> Self { Self { colors : FIRE_PALETTE.to_vec() } }
+
+	pub fn len(&self) -> usize { self.colors.len() }
+
+	pub fn is_empty(&self) -> bool { self.colors.is_empty() }
+
+	// n colors linearly interpolated between (position, rgb) stops sorted by position
+	pub fn gradient(stops : &[(f32, [u8; 3])], n : usize) -> Self {
+		Self { colors : (0..n).map(|i| {
+			let t = i as f32 / (n - 1).max(1) as f32;
+			let k = stops.iter().position(|s| s.0 >= t).unwrap_or(stops.len() - 1);
+			if k == 0 || stops[k].0 <= t { return rgb(stops[k].1) }
+			let ((t0, c0), (t1, c1)) = (stops[k - 1], stops[k]);
+			let f = (t - t0) / (t1 - t0);
+			let mut c = [0_u8; 3];
+			for j in 0..3 { c[j] = (c0[j] as f32 + (c1[j] as f32 - c0[j] as f32) * f).round() as u8 }
+			rgb(c)
+		}).collect() }
+	}
+
+	pub fn from_map(name : &str, text : &str) -> io::Result<Self> {
+		let colors = lines(text).map(|(n, v)| parse_rgb(&v[..v.len().min(3)]).map(rgb).ok_or_else(|| invalid(name, n, "expected 'r g b'")))
+			.collect::<io::Result<Vec<_>>>()?;
+		if colors.is_empty() { return Err(invalid(name, 0, "no colors")) }
+		Ok(Self { colors })
+	}
+
+	pub fn from_stops(name : &str, text : &str) -> io::Result<Self> {
+		let mut stops = lines(text).map(|(n, v)| match (v[0].parse::<f32>(), parse_rgb(&v[1..])) {
+			(Ok(t), Some(c)) if (0. ..=1.).contains(&t) => Ok((t, c)),
+			_ => Err(invalid(name, n, "expected 'position #rrggbb' or 'position r g b', position in 0..1")),
+		}).collect::<io::Result<Vec<_>>>()?;
+		if stops.is_empty() { return Err(invalid(name, 0, "no color stops")) }
+		stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+		Ok(Self::gradient(&stops, GRADIENT_SIZE))
+	}
+
+	pub fn load(name : &str) -> io::Result<Self> { // format by extension, .map or gradient stops
+		let text = std::fs::read_to_string(name)?;
+		if name.ends_with(".map") { Self::from_map(name, &text) } else { Self::from_stops(name, &text) }
+	}
+
+	// color at fractional index x, wraps around, linear between entries
+	pub fn lerp(&self, x : f32) -> u32 {
+		let n = self.len();
+		let i = x.floor();
+		let (f, i) = (x - i, (i as i64).rem_euclid(n as i64) as usize);
+		let (c0, c1) = (rgb_components(self.colors[i]), rgb_components(self.colors[(i + 1) % n]));
+		let mut c = [0_u8; 3];
+		for j in 0..3 { c[j] = (c0[j] + (c1[j] - c0[j]) * f).round() as u8 }
+		rgb(c)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn palettes() {
+		let map = Palette::from_map("t.map", "0 0 0 black\n# comment\n\n255 128 0\n").unwrap();
+		assert_eq!(map.colors, vec![rgb([0, 0, 0]), rgb([255, 128, 0])]);
+		assert!(Palette::from_map("t.map", "0 0\n").is_err());
+		assert_eq!(map.lerp(0.5), rgb([128, 64, 0]));
+		assert_eq!(map.lerp(1.5), rgb([128, 64, 0])); // wraps back to black
+		assert_eq!(map.lerp(2.), map.colors[0]);
+
+		let g = Palette::from_stops("t.txt", "1 #ffffff\n0 0 0 0\n0.5 #ff0000\n").unwrap();
+		assert_eq!(g.len(), GRADIENT_SIZE);
+		assert_eq!((g.colors[0], g.colors[255]), (rgb([0, 0, 0]), rgb([255, 255, 255])));
+		assert_eq!(rgb_components(g.colors[128])[0], 255.);
+		assert!(Palette::from_stops("t.txt", "1.5 #ffffff\n").is_err());
+		let err = Palette::from_stops("t.txt", "0 #000000\n0.5 #fff\n").unwrap_err();
+		assert!(err.to_string().starts_with("t.txt:2:"));
+	}
+}
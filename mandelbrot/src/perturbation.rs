@@ -0,0 +1,199 @@
+/*
+	deep zoom by perturbation, z*z + c family
+
+	one reference orbit Z at the view center in arbitrary precision fixed point (BigInt, 'bits' fractional bits),
+	every pixel iterates only its f64 delta to it, z = Z + dz, c = C + dc:
+		dz(n+1) = (2 Z(n) + dz(n)) dz(n) + dc
+
+	glitch: when |Z + dz| < |dz| the delta dominates the orbit and loses its precision, it's detected per step
+	and corrected by rebasing the pixel onto the start of the reference orbit, dz = Z + dz, n = 0.
+	the same rebase continues pixels past the end of an escaped reference orbit.
+*/
+
+use num::{BigInt, Zero, ToPrimitive};
+use num::complex::Complex64;
+use rayon::prelude::*;
+
+const GUARD_BITS : usize = 64; // extra precision beyond the pixel size
+
+// fixed point complex, value / 2^bits
+#[derive(Clone, Debug, PartialEq)]
+pub struct BigComplex {
+	pub re : BigInt,
+	pub im : BigInt,
+}
+
+// decimal string '-0.7436438870371587047521915' to fixed point
+pub fn parse_fixed(s : &str, bits : usize) -> Result<BigInt, String> {
+	let err = || format!("bad decimal number '{}'", s);
+	let (neg, digits) = match s.trim().strip_prefix('-') { Some(d) => (true, d), None => (false, s.trim().trim_start_matches('+')) };
+	let (int, frac) = match digits.find('.') { Some(p) => (&digits[..p], &digits[p + 1..]), None => (digits, "") };
+	if int.is_empty() && frac.is_empty() || !(int.bytes().chain(frac.bytes())).all(|b| b.is_ascii_digit()) { return Err(err()) }
+
+	let mantissa = BigInt::parse_bytes(format!("0{}{}", int, frac).as_bytes(), 10).ok_or_else(err)?;
+	let v = (mantissa << bits) / num::pow(BigInt::from(10), frac.len());
+	Ok(if neg { -v } else { v })
+}
+
+pub fn fixed_to_f64(v : &BigInt, bits : usize) -> f64 { // keeps 64 significant bits whatever the magnitude
+	let shift = (v.bits() as usize).saturating_sub(64);
+	(v >> shift).to_f64().unwrap_or(f64::NAN) * 2_f64.powi(shift as i32 - bits as i32)
+}
+
+pub fn f64_to_fixed(v : f64, bits : usize) -> BigInt { // exact, truncated below 2^-bits
+	let (mantissa, exponent, sign) = num::Float::integer_decode(v);
+	let m = BigInt::from(mantissa) * sign;
+	let shift = bits as i64 + exponent as i64;
+	if shift >= 0 { m << shift as usize } else { m >> (-shift) as usize }
+}
+
+impl BigComplex {
+	pub fn to_c64(&self, bits : usize) -> Complex64 { Complex64::new(fixed_to_f64(&self.re, bits), fixed_to_f64(&self.im, bits)) }
+
+	pub fn sqr_add(&self, c : &BigComplex, bits : usize) -> BigComplex { // self^2 + c
+		BigComplex {
+			re : ((&self.re * &self.re - &self.im * &self.im) >> bits) + &c.re,
+			im : ((&self.re * &self.im) >> (bits - 1)) + &c.im,
+		}
+	}
+}
+
+// reference center, pixel deltas come from the viewport width & rotation
+#[derive(Clone, Debug)]
+pub struct DeepZoom {
+	pub center : BigComplex,
+	pub bits   : usize,
+}
+
+impl DeepZoom {
+	pub fn new(re : &str, im : &str, width : f64) -> Result<Self, String> { // precision for views down to width
+		if !width.is_finite() || width <= 0. { return Err(format!("bad width {}", width)) }
+		let bits = (-width.log2()).max(0.) as usize + GUARD_BITS;
+		Ok(Self { center : BigComplex { re : parse_fixed(re, bits)?, im : parse_fixed(im, bits)? }, bits })
+	}
+
+	pub fn center_c64(&self) -> Complex64 { self.center.to_c64(self.bits) }
+
+	// Z(0) = 0, Z(1) = C.. until |Z| > bailout or n = iters+1
+	pub fn reference_orbit(&self, iters : u32, bailout : f64) -> Vec<Complex64> {
+		let mut z = BigComplex { re : BigInt::zero(), im : BigInt::zero() };
+		let mut orbit = vec![Complex64::new(0., 0.)];
+		for _ in 0..=iters {
+			z = z.sqr_add(&self.center, self.bits);
+			let zf = z.to_c64(self.bits);
+			orbit.push(zf);
+			if zf.norm_sqr() > bailout * bailout { break }
+		}
+		orbit
+	}
+
+	// iterations counted as the f32 loop: z = c, then z*z + c until |z| > bailout
+	pub fn escape(orbit : &[Complex64], dc : Complex64, iters : u32, bailout : f64) -> (u32, Complex64) {
+		let (mut dz, mut n) = (dc, 1);
+		let mut z = orbit[1] + dz;
+
+		let mut ix : u32 = 0;
+		while ix < iters {
+			if n + 1 == orbit.len() { // end of reference, also a reference escaped at once: rebase
+				dz = z;
+				n = 0;
+			}
+			dz = (2. * orbit[n] + dz) * dz + dc;
+			n += 1;
+			z = orbit[n] + dz;
+			if z.norm_sqr() > bailout * bailout { break }
+			if z.norm_sqr() < dz.norm_sqr() { // glitch: rebase
+				dz = z;
+				n = 0;
+			}
+
+			ix += 1;
+		}
+		(ix, z)
+	}
+
+	// offset(i, j): pixel center relative to the reference
+	pub fn escape_all(&self, w : u32, h : u32, offset : impl Fn(u32, u32) -> Complex64 + Sync, iters : u32, bailout : f64) -> Vec<(u32, Complex64)> {
+		let orbit = self.reference_orbit(iters, bailout);
+		(0..w * h).into_par_iter().map(|index| Self::escape(&orbit, offset(index % w, index / w), iters, bailout)).collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::viewport::Viewport;
+
+	#[test]
+	fn fixed_point() {
+		let bits = 80;
+		assert_eq!(parse_fixed("1", bits).unwrap(), BigInt::from(1) << bits);
+		assert_eq!(fixed_to_f64(&parse_fixed("-0.75", bits).unwrap(), bits), -0.75);
+		assert_eq!(fixed_to_f64(&parse_fixed(".5", bits).unwrap(), bits), 0.5);
+		assert!((fixed_to_f64(&parse_fixed("0.1234567890123456789", bits).unwrap(), bits) - "0.1234567890123456789".parse::<f64>().unwrap()).abs() < 1e-17);
+		let tiny = fixed_to_f64(&parse_fixed("-0.000000000000000000000000000012345678901234567", 160).unwrap(), 160); // relative, not absolute precision
+		assert!((tiny / -1.2345678901234567e-29 - 1.).abs() < 1e-15);
+		for &v in [0., 1.5, -0.75, 3e-20, -1.2345e-25].iter() { assert_eq!(fixed_to_f64(&f64_to_fixed(v, 160), 160), v) }
+		for bad in ["", "-", "1.2.3", "1e5", "abc"].iter() { assert!(parse_fixed(bad, bits).is_err()) }
+	}
+
+	#[test]
+	fn deep_zoom() { // perturbation vs brute force arbitrary precision at 1e-30
+		let (w, h, iters, bailout) = (16, 16, 50000, 2.);
+		let dz = DeepZoom::new("-0.743643887037158704752191506114774", "0.131825904205311970493132056385139", 1e-30).unwrap();
+		let vp = Viewport::new(dz.center_c64(), 1e-30).with_rotation(0.3);
+		let counts = dz.escape_all(w, h, |i, j| vp.offset(i, j, w, h), iters, bailout);
+
+		let fixed = |v : f64| f64_to_fixed(v, dz.bits); // f64 pixel deltas are exact in fixed point
+		let pixel = |i : u32, j : u32| {
+			let dc = vp.offset(i, j, w, h);
+			let c = BigComplex { re : &dz.center.re + fixed(dc.re), im : &dz.center.im + fixed(dc.im) };
+			let mut z = c.clone();
+			let mut ix = 0;
+			while ix < iters {
+				z = z.sqr_add(&c, dz.bits);
+				if z.to_c64(dz.bits).norm() > bailout { break }
+				ix += 1;
+			}
+			ix
+		};
+		let mut distinct = counts.iter().map(|c| c.0).collect::<Vec<_>>();
+		distinct.sort_unstable();
+		distinct.dedup();
+		assert!(distinct.len() > 5, "no detail at 1e-30: {:?}", distinct);
+
+		for &(i, j) in [(0, 0), (3, 11), (8, 8), (15, 2), (10, 14)].iter() {
+			let (ix, bf) = (counts[(j * w + i) as usize].0, pixel(i, j));
+			assert!((ix as i64 - bf as i64).abs() <= 1, "pixel {},{}: perturbation {} brute force {}", i, j, ix, bf);
+		}
+	}
+
+	#[test]
+	fn escaped_reference() { // reference orbit Z(0), Z(1) only, every pixel runs on rebases
+		let (w, h, iters, bailout) = (16, 16, 200, 2.);
+		let dz = DeepZoom::new("3", "0", 1.).unwrap();
+		assert_eq!(dz.reference_orbit(iters, bailout).len(), 2);
+		let vp = Viewport::new(dz.center_c64(), 7.); // takes in the set
+		let counts = dz.escape_all(w, h, |i, j| vp.offset(i, j, w, h), iters, bailout);
+
+		let pixel = |i : u32, j : u32| { // f64 brute force
+			let c = vp.pixel_to_c(i, j, w, h);
+			let (mut z, mut ix) = (c, 0);
+			while ix < iters {
+				z = z * z + c;
+				if z.norm() > bailout { break }
+				ix += 1;
+			}
+			ix
+		};
+		let mut differ = 0;
+		for j in 0..h {
+			for i in 0..w {
+				let (ix, bf) = (counts[(j * w + i) as usize].0, pixel(i, j));
+				assert!((ix as i64 - bf as i64).abs() <= 1, "pixel {},{}: perturbation {} brute force {}", i, j, ix, bf);
+				if ix != bf { differ += 1 }
+			}
+		}
+		assert!(differ < 4 && counts.iter().any(|c| c.0 == iters) && counts[0].0 == 0, "{} pixels differ", differ);
+	}
+}
@@ -0,0 +1,201 @@
+/*
+	out of core rendering, images larger than memory
+
+	the image is cut in horizontal strips, each one a window render of its own (any family, smooth,
+	interior tests, shading, deep zoom), a batch of strips is rendered in parallel and saved as raw rgb to
+	'name.strips/strip_000000.rgb'.. as soon as it's done. an interrupted render only renders the missing
+	strips when restarted, 'render.txt' there records the parameters so a changed render doesn't reuse old strips.
+	the png is then streamed from the strip files: paeth filtered rows -> zlib -> IDAT chunks.
+	memory: a batch of strips, disk: 3 bytes per pixel until the png is complete.
+*/
+
+use crate::mandelbrot::*;
+use rayon::prelude::*;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+const STRIP_PIXELS : u32 = 1 << 20; // default strip size
+const IDAT_SIZE : usize = 1 << 16;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TiledRender {
+	pub strip_rows 	: u32, // 0: about STRIP_PIXELS per strip
+	pub keep_strips : bool, // else removed once the png is written
+}
+
+impl TiledRender {
+	pub fn with_strip_rows(mut self, strip_rows : u32) -> Self {
+		self.strip_rows = strip_rows;
+		self
+	}
+
+	pub fn with_keep_strips(mut self, keep_strips : bool) -> Self {
+		self.keep_strips = keep_strips;
+		self
+	}
+
+	fn rows(&self, w : u32) -> u32 { if self.strip_rows > 0 { self.strip_rows } else { (STRIP_PIXELS / w).max(1) } }
+}
+
+// IDAT chunks of the zlib stream
+struct IdatWriter<'a, W : Write> {
+	png : &'a mut png::Writer<W>,
+	buf : Vec<u8>,
+}
+
+impl<'a, W : Write> Write for IdatWriter<'a, W> {
+	fn write(&mut self, data : &[u8]) -> io::Result<usize> {
+		self.buf.extend_from_slice(data);
+		if self.buf.len() >= IDAT_SIZE { self.flush()? }
+		Ok(data.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		if !self.buf.is_empty() {
+			self.png.write_chunk(png::chunk::IDAT, &self.buf)?;
+			self.buf.clear();
+		}
+		Ok(())
+	}
+}
+
+fn paeth(a : u8, b : u8, c : u8) -> u8 { // left, up, up left
+	let p = a as i16 + b as i16 - c as i16;
+	let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+	if pa <= pb && pa <= pc { a } else if pb <= pc { b } else { c }
+}
+
+// filter type byte + paeth differences of an rgb row
+fn filter_row(row : &[u8], prev : &[u8], out : &mut Vec<u8>) {
+	out.clear();
+	out.push(4);
+	for k in 0..row.len() {
+		let (a, c) = if k >= 3 { (row[k - 3], prev[k - 3]) } else { (0, 0) };
+		out.push(row[k].wrapping_sub(paeth(a, prev[k], c)));
+	}
+}
+
+fn invalid(msg : String) -> io::Error { io::Error::new(io::ErrorKind::InvalidInput, msg) }
+
+impl Mandelbrot {
+	fn strip_manifest(&self, rows : u32) -> String {
+		format!("{} x {}, {} rows per strip\n{:?}\n{:?}\n{:?}\niters {} cycle {} smooth {} interior {} shading {:?}\npalette {:x?}\ndeep {:?}\n",
+			self.w, self.h, rows, self.viewport, self.whole_frame(), self.fractal, self.iters, self.cycle, self.smooth, self.interior, self.shading, self.palette.colors, self.deep)
+	}
+
+	fn render_strip(&self, y0 : u32, rows : u32, path : &Path) -> io::Result<()> {
+		let mut strip = self.window(0, y0, self.w, rows);
+		strip.generate();
+		let rgb : Vec<u8> = (0..strip.image.len()).flat_map(|index| strip.get_pixel_rgb(index)).collect();
+
+		let tmp = path.with_extension("tmp"); // complete strips only under their final name
+		fs::write(&tmp, rgb)?;
+		fs::rename(&tmp, path)
+	}
+
+	// renders into 'name' strip by strip with bounded memory, resumes from 'name.strips' if present
+	pub fn render_tiled(&self, name : &str, tiled : &TiledRender) -> io::Result<()> {
+		if self.w == 0 || self.h == 0 { return Err(invalid(format!("empty image {} x {}", self.w, self.h))) }
+		if self.equalize { return Err(invalid("histogram equalization needs the whole image, not available in tiled renders".to_string())) }
+		let rows = tiled.rows(self.w);
+		let strips = self.h.div_ceil(rows);
+		let strip_rows = |k : u32| rows.min(self.h - k * rows);
+
+		let dir = PathBuf::from(format!("{}.strips", name));
+		let manifest = self.strip_manifest(rows);
+		fs::create_dir_all(&dir)?;
+		match fs::read_to_string(dir.join("render.txt")) {
+			Ok(old) if old != manifest => return Err(invalid(format!("{} holds strips of another render, remove it", dir.display()))),
+			Ok(_) => {}
+			Err(_) => fs::write(dir.join("render.txt"), &manifest)?,
+		}
+		let strip_path = |k : u32| dir.join(format!("strip_{:06}.rgb", k));
+
+		let todo : Vec<u32> = (0..strips).filter(|&k| {
+			fs::metadata(strip_path(k)).map_or(true, |m| m.len() != 3 * (self.w * strip_rows(k)) as u64)
+		}).collect();
+		for batch in todo.chunks(rayon::current_num_threads()) {
+			batch.par_iter().map(|&k| self.render_strip(k * rows, strip_rows(k), &strip_path(k))).collect::<io::Result<()>>()?;
+		}
+
+		self.write_png_strips(name, strips, strip_path)?;
+		if !tiled.keep_strips { fs::remove_dir_all(&dir)? }
+		Ok(())
+	}
+
+	// streams the strip files into one png, a row at a time
+	fn write_png_strips(&self, name : &str, strips : u32, strip_path : impl Fn(u32) -> PathBuf) -> io::Result<()> {
+		let mut encoder = png::Encoder::new(BufWriter::new(File::create(name)?), self.w, self.h);
+		encoder.set_color(png::ColorType::RGB);
+		encoder.set_depth(png::BitDepth::Eight);
+		let mut writer = encoder.write_header()?;
+
+		let mut zlib = deflate::write::ZlibEncoder::new(IdatWriter { png : &mut writer, buf : Vec::with_capacity(IDAT_SIZE) }, deflate::Compression::Default);
+		let stride = 3 * self.w as usize;
+		let (mut row, mut prev, mut filtered) = (vec![0_u8; stride], vec![0_u8; stride], Vec::with_capacity(stride + 1));
+		for k in 0..strips {
+			let file = File::open(strip_path(k))?;
+			let lines = file.metadata()?.len() as usize / stride;
+			let mut input = BufReader::new(file);
+			for _ in 0..lines {
+				input.read_exact(&mut row)?;
+				filter_row(&row, &prev, &mut filtered);
+				zlib.write_all(&filtered)?;
+				std::mem::swap(&mut row, &mut prev);
+			}
+		}
+		zlib.finish()?.flush()?;
+		drop(writer); // IEND
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use num::complex::Complex64;
+	use std::sync::Arc;
+
+	fn read_png(name : &Path) -> Vec<u8> { image::open(name).unwrap().into_rgb8().into_raw() }
+
+	#[test]
+	fn tiled() {
+		let dir = std::env::temp_dir();
+		let (whole, tiled) = (dir.join("mandel_tiled_whole.png"), dir.join("mandel_tiled.png"));
+		let mut mnd = Mandelbrot::new(97, 61, Viewport::new(Complex64::new(-0.75, 0.1), 0.5).with_rotation(0.2), 300).with_smooth(true);
+		mnd.generate();
+		mnd.write_png(whole.to_str().unwrap());
+
+		let t = TiledRender::default().with_strip_rows(8).with_keep_strips(true);
+		mnd.render_tiled(tiled.to_str().unwrap(), &t).unwrap();
+		let (a, b) = (read_png(&whole), read_png(&tiled));
+		assert_eq!(a.len(), b.len());
+		assert!(a == b, "tiled and whole renders differ");
+
+		// resume: strip 2 is kept as is, strip 5 is rendered again
+		let strips = dir.join("mandel_tiled.png.strips");
+		fs::write(strips.join("strip_000002.rgb"), vec![0_u8; 3 * 97 * 8]).unwrap();
+		fs::remove_file(strips.join("strip_000005.rgb")).unwrap();
+		mnd.render_tiled(tiled.to_str().unwrap(), &t.with_keep_strips(false)).unwrap();
+		let c = read_png(&tiled);
+		assert!(c[3 * 97 * 16..3 * 97 * 24].iter().all(|&v| v == 0));
+		assert_eq!(c[3 * 97 * 40..3 * 97 * 48], b[3 * 97 * 40..3 * 97 * 48]);
+		assert!(!strips.exists());
+
+		// other parameters, family or its constants don't reuse strips
+		mnd.render_tiled(tiled.to_str().unwrap(), &t).unwrap();
+		assert!(mnd.window(0, 0, 0, 61).render_tiled(tiled.to_str().unwrap(), &TiledRender::default()).is_err());
+		assert!(Mandelbrot { iters : 100, ..mnd.window(0, 0, 97, 61) }.render_tiled(tiled.to_str().unwrap(), &t).is_err());
+		assert!(mnd.window(0, 0, 97, 61).with_interior(true).render_tiled(tiled.to_str().unwrap(), &t).is_err());
+		for fractal in [Arc::new(Julia { c : ComplexF32::new(-0.8, 0.156) }) as Arc<dyn EscapeTime>, Arc::new(Tricorn)] {
+			assert!(Mandelbrot { fractal, ..mnd.window(0, 0, 97, 61) }.render_tiled(tiled.to_str().unwrap(), &t).is_err());
+		}
+		let julia = |c : ComplexF32| mnd.window(0, 0, 97, 61).with_fractal(Julia { c }).strip_manifest(8);
+		assert_ne!(julia(ComplexF32::new(-0.8, 0.156)), julia(ComplexF32::new(-0.8, 0.2)));
+		mnd.window(0, 0, 97, 61).render_tiled(tiled.to_str().unwrap(), &t).unwrap(); // same render resumes
+		fs::remove_dir_all(strips).unwrap();
+		let _ = fs::remove_file(whole);
+		let _ = fs::remove_file(tiled);
+	}
+}
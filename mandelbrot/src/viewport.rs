@@ -0,0 +1,106 @@
+/*
+	view of the complex plane: center, real axis width & rotation, height follows the image aspect ratio
+	pixel (0,0) is the top left corner, im grows upwards
+*/
+
+use num::complex::Complex64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+	pub center 	 : Complex64,
+	pub width 	 : f64,
+	pub rotation : f64, // radians, counterclockwise
+}
+
+impl Default for Viewport {
+	fn default() -> Self { Self::new(Complex64::new(-0.5, 0.), 3.2) }
+}
+
+impl Viewport {
+	pub fn new(center : Complex64, width : f64) -> Self { Self { center, width, rotation : 0. } }
+
+	pub fn with_rotation(mut self, rotation : f64) -> Self {
+		self.rotation = rotation;
+		self
+	}
+
+	pub fn height(&self, w : u32, h : u32) -> f64 { self.width * h as f64 / w as f64 }
+
+	// pixel center relative to the view center
+	pub fn offset(&self, i : u32, j : u32, w : u32, h : u32) -> Complex64 {
+		let scale = self.width / w as f64;
+		let d = Complex64::new((i as f64 + 0.5 - w as f64 / 2.) * scale, (h as f64 / 2. - j as f64 - 0.5) * scale);
+		if self.rotation == 0. { d } else { d * Complex64::from_polar(1., self.rotation) }
+	}
+
+	pub fn pixel_to_c(&self, i : u32, j : u32, w : u32, h : u32) -> Complex64 { self.center + self.offset(i, j, w, h) }
+
+	// pixel containing c, None outside the image
+	pub fn c_to_pixel(&self, c : Complex64, w : u32, h : u32) -> Option<(u32, u32)> {
+		let d = c - self.center;
+		let d = if self.rotation == 0. { d } else { d * Complex64::from_polar(1., -self.rotation) };
+		let scale = self.width / w as f64;
+		let (x, y) = ((d.re / scale + w as f64 / 2.).floor(), (h as f64 / 2. - d.im / scale).floor());
+		if x >= 0. && y >= 0. && x < w as f64 && y < h as f64 { Some((x as u32, y as u32)) } else { None }
+	}
+
+	// same center & rotation, width scaled by 1/factor
+	pub fn zoom(&self, factor : f64) -> Self { Self { width : self.width / factor, ..*self } }
+}
+
+// ww x wh pixels at (x0, y0) of a w x h image, mapped through the viewport of the whole image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+	pub x0 : u32,
+	pub y0 : u32,
+	pub w  : u32,
+	pub h  : u32,
+}
+
+// exponential zoom into the view center, frame k has width w0 * (end_width/w0)^(k/(frames-1))
+// and iters0 * iter_growth^k iterations
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ZoomAnimation {
+	pub frames 		: u32,
+	pub end_width 	: f64,
+	pub iter_growth : f64,
+}
+
+impl ZoomAnimation {
+	pub fn frame(&self, start : &Viewport, iters : u32, k : u32) -> (Viewport, u32) {
+		let t = if self.frames > 1 { k as f64 / (self.frames - 1) as f64 } else { 0. };
+		let vp = Viewport { width : start.width * (self.end_width / start.width).powf(t), ..*start };
+		(vp, (iters as f64 * self.iter_growth.powi(k as i32)).round().min(u32::MAX as f64) as u32)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn mapping() {
+		let vp = Viewport::new(Complex64::new(1., 2.), 4.);
+		let (w, h) = (400, 200);
+		assert_eq!(vp.height(w, h), 2.);
+		assert!((vp.pixel_to_c(0, 0, w, h) - Complex64::new(-0.995, 2.995)).norm() < 1e-12); // top left
+		assert!((vp.pixel_to_c(w - 1, h - 1, w, h) - Complex64::new(2.995, 1.005)).norm() < 1e-12);
+
+		let rot = vp.with_rotation(std::f64::consts::FRAC_PI_2); // right edge goes up
+		assert!((rot.offset(w - 1, h / 2, w, h) - Complex64::new(0.005, 1.995)).norm() < 1e-12);
+		assert!((rot.offset(0, 0, w, h) - vp.offset(0, 0, w, h) * Complex64::i()).norm() < 1e-12);
+
+		for &(i, j) in [(0, 0), (17, 33), (w - 1, h - 1)].iter() {
+			assert_eq!(vp.c_to_pixel(vp.pixel_to_c(i, j, w, h), w, h), Some((i, j)));
+			assert_eq!(rot.c_to_pixel(rot.pixel_to_c(i, j, w, h), w, h), Some((i, j)));
+		}
+		assert_eq!(vp.c_to_pixel(Complex64::new(3.1, 2.), w, h), None);
+
+		let anim = ZoomAnimation { frames : 11, end_width : 4e-10, iter_growth : 1.1 };
+		assert_eq!(anim.frame(&vp, 100, 0), (vp, 100));
+		let (mid, iters) = anim.frame(&vp, 100, 5);
+		assert!((mid.width / 4e-5 - 1.).abs() < 1e-9 && mid.center == vp.center);
+		assert_eq!(iters, 161);
+		assert!((anim.frame(&vp, 100, 10).0.width / 4e-10 - 1.).abs() < 1e-9);
+	}
+}
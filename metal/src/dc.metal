@@ -179,6 +179,11 @@ inline ComplexFloat sinh(ComplexFloat z) { return z.sinh(); }
 inline ComplexFloat cosh(ComplexFloat z) { return z.cosh(); }
 inline ComplexFloat pow(ComplexFloat x, ComplexFloat y) { return x.pow(y); }
 
+// ZVm Dialect::Metal expressions: prelude & float2 zvm_func(float2 z) prefixed to this source,
+// %%FUNC%% = from_cplx(zvm_func(to_cplx(z)))
+inline ComplexFloat from_cplx(float2 a) { return ComplexFloat(a.x, a.y); }
+inline float2 to_cplx(ComplexFloat z) { return float2(z.re, z.im); }
+
 // the complex func %%FUNC%%
 // sed s/%%FUNC%%/"z*z"/g dc.metal > dcz.metal
 ComplexFloat z_func(ComplexFloat z)  { return %%FUNC%%; }
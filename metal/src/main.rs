@@ -4,6 +4,11 @@ use metal_wrap::*;
 use objc::rc::autoreleasepool;
 use std::time::Instant;
 
+#[allow(dead_code)]
+#[path = "../../ocl/src/zvm.rs"]
+mod zvm;
+use zvm::{ZVm, Dialect};
+
 
 fn domain_coloring(){
     static DC_METAL: &str = include_str!("dc.metal");
@@ -34,6 +39,36 @@ fn domain_coloring(){
     });
 }
 
+// z expression transpiled to metal, in dc.metal's z_func
+fn domain_coloring_src(expr : &str) {
+    static DC_METAL: &str = include_str!("dc.metal");
+
+    let zvm = match ZVm::new(expr) {
+        Ok(zvm) => zvm,
+        Err(err) => { eprintln!("z expression {}", err); return }
+    };
+    let source = format!("{}\nfloat2 zvm_func(float2 z) {{ return {}; }}\n{}",
+        Dialect::Metal.prelude(), zvm.to_source(Dialect::Metal), DC_METAL.replace("%%FUNC%%", "from_cplx(zvm_func(to_cplx(z)))"));
+
+    let side = 2000;
+    let size   = (side * side) as usize;
+
+    autoreleasepool( || {
+        
+        let metal = Metal::new_src(&source, "DomainColoring");
+        
+        let buffs = metal_buffers![metal; 
+            vec![0_u32; size], // image, side
+            vec![side] ];
+
+        let t = Instant::now();
+        metal.run(side as usize);
+        println!("DomainColoring {} {} x {} = {}, lap: {:.1?}", expr, side, side, size, Instant::now() - t);
+
+        write_bin("dc.bin", buffer_2_image(&buffs[0])); // showbinimage.py 2000 2000 dc.bin 
+    });
+}
+
 fn mandelbrot() {
     let side = 2000;
     let size   = (side * side) as usize;
@@ -88,5 +123,8 @@ fn voronoi() {
 }
 
 fn main() {
-    domain_coloring()
+    match std::env::args().nth(1) { // metal-app [z expression]: domain coloring of the expression
+        Some(expr) => domain_coloring_src(&expr),
+        None => domain_coloring(),
+    }
 }
@@ -0,0 +1,71 @@
+// Domain Coloring pixel mapping & colors
+// dc_color.cl, prefixed to the dc_zvm.cl and dc_src.cl kernels
+
+uint rgbf2uint(float r, float g, float b) {
+  return 0xff000000u | // alpha 0xff
+         ((uint)(r * 255) & 0xffu) | (((uint)(g * 255) & 0xffu) << 8) |
+         (((uint)(b * 255) & 0xffu) << 16);
+}
+
+uint HSV2RGB(float h, float s, float v) { // convert hsv to rgb,1
+  float3 res;
+
+  if (s == 0) {
+    res = (float3)(v, v, v);
+  } else {
+    if (h == 1)
+      h = 0;
+
+    float z = floor(h * 6), f = h * 6 - z, p = v * (1 - s), q = v * (1 - s * f),
+          t = v * (1 - s * (1 - f));
+
+    switch ((int)(z) % 6) {
+    case 0:
+      res = (float3)(v, t, p);
+      break;
+    case 1:
+      res = (float3)(q, v, p);
+      break;
+    case 2:
+      res = (float3)(p, v, t);
+      break;
+    case 3:
+      res = (float3)(p, q, v);
+      break;
+    case 4:
+      res = (float3)(t, p, v);
+      break;
+    case 5:
+      res = (float3)(v, p, q);
+      break;
+    }
+  }
+  return rgbf2uint(res.x, res.y, res.z);
+}
+
+// pixel (x, y) of a w x h image in [-pi, pi]^2, re to the right, im up
+float2 dc_pixel_z(int x, int y, int w, int h) {
+  float PI = 3.141592653f;
+  float limit = PI, rmi = -limit, rma = limit, imi = -limit, ima = limit;
+
+  return (float2)(rmi + (rma - rmi) * x / (w - 1),
+                  ima - (ima - imi) * y / (h - 1));
+}
+
+// color of f(z) = v
+uint dc_color(float2 v) {
+  float E = 2.7182818284f, PI = 3.141592653f, PI2 = PI * 2;
+
+  float m, ranges, rangee; //  prop. e^n < m < e^(n-1)
+  for (m = length(v), ranges = 0, rangee = 1; m > rangee; rangee *= E)
+    ranges = rangee;
+
+  float k = (m - ranges) / (rangee - ranges),
+        kk = (k < 0.5f ? k * 2 : 1 - (k - 0.5f) * 2);
+
+  float ang = fmod(fabs(atan2(v.y, v.x)), PI2) / PI2, // -> hsv
+      sat = 0.4f + (1 - pow(1 - kk, 3)) * 0.6f,
+        val = 0.6f + (1 - pow(1 - (1 - kk), 3)) * 0.4f;
+
+  return HSV2RGB(ang, sat, val);
+}
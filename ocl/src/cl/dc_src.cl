@@ -0,0 +1,14 @@
+// Domain Coloring of a native z expression
+// dc_src.cl, prefixed by ZVm Dialect::OpenCL.prelude(), domain_color_func and dc_color.cl
+
+kernel void domain_coloring(global uint *image // 0: image
+) {
+  size_t index = get_global_id(0);
+  int width = (int)sqrt((float)get_global_size(0)); // w x w = n
+
+  int x = (int)(index % width),
+      y = (int)(index / width); // point(x,y)
+
+  float2 z = dc_pixel_z(x, y, width, width);
+  image[index] = dc_color(domain_color_func(z)); // transpiled domain coloring func
+}
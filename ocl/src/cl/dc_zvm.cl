@@ -1,5 +1,5 @@
 // Domain Coloring unsing zvm
-// dc_zvm.cl
+// dc_zvm.cl, prefixed by dc_color.cl
 
 // complex arithmetics: +,-, neg direct float2 support
 float2 mul(float2 a, float2 b) {
@@ -73,48 +73,6 @@ uint argbf2uint(uint alpha, float r, float g, float b) {
          (((uint)(255.f * b) & 0xffu) << 16);
 }
 
-uint rgbf2uint(float r, float g, float b) {
-  return 0xff000000u | // alpha 0xff
-         ((uint)(r * 255) & 0xffu) | (((uint)(g * 255) & 0xffu) << 8) |
-         (((uint)(b * 255) & 0xffu) << 16);
-}
-
-uint HSV2RGB(float h, float s, float v) { // convert hsv to rgb,1
-  float3 res;
-
-  if (s == 0) {
-    res = (float3)(v, v, v);
-  } else {
-    if (h == 1)
-      h = 0;
-
-    float z = floor(h * 6), f = h * 6 - z, p = v * (1 - s), q = v * (1 - s * f),
-          t = v * (1 - s * (1 - f));
-
-    switch ((int)(z) % 6) {
-    case 0:
-      res = (float3)(v, t, p);
-      break;
-    case 1:
-      res = (float3)(q, v, p);
-      break;
-    case 2:
-      res = (float3)(p, v, t);
-      break;
-    case 3:
-      res = (float3)(p, q, v);
-      break;
-    case 4:
-      res = (float3)(t, p, v);
-      break;
-    case 5:
-      res = (float3)(v, p, q);
-      break;
-    }
-  }
-  return rgbf2uint(res.x, res.y, res.z);
-}
-
 float2 domain_color_func(float2); // domain coloring func
 
 // zvm evaluator
@@ -245,28 +203,12 @@ finished:
 }
 
 uint dc_get_color(int x, int y, int w, int h, global uint *code) {
-
-  float E = 2.7182818284f, PI = 3.141592653f, PI2 = PI * 2;
-  float limit = PI, rmi = -limit, rma = limit, imi = -limit, ima = limit;
-
-  float2 z = (float2)(ima - (ima - imi) * y / (h - 1),
-                      rma - (rma - rmi) * x / (w - 1));
+  float2 z = dc_pixel_z(x, y, w, h);
 
   // float2 v = domain_color_func(z); // fixed evaluate domain coloring func
   float2 v = eval_zvn(z, code); // evaluate ZVM domain coloring func
 
-  float m, ranges, rangee; //  prop. e^n < m < e^(n-1)
-  for (m = length(v), ranges = 0, rangee = 1; m > rangee; rangee *= E)
-    ranges = rangee;
-
-  float k = (m - ranges) / (rangee - ranges),
-        kk = (k < 0.5f ? k * 2 : 1 - (k - 0.5f) * 2);
-
-  float ang = fmod(fabs(arg(v)), PI2) / PI2, // -> hsv
-      sat = 0.4f + (1 - pow(1 - kk, 3)) * 0.6f,
-        val = 0.6f + (1 - pow(1 - (1 - kk), 3)) * 0.4f;
-
-  return HSV2RGB(ang, sat, val);
+  return dc_color(v);
 }
 
 /////////////////////// example of fixed domain coloring func
@@ -285,8 +227,8 @@ kernel void domain_coloring(global uint *image, // 0: image
   size_t index = get_global_id(0);
   int width = (int)sqrt((float)get_global_size(0)); // w x w = n
 
-  int x = (int)(index / width),
-      y = (int)(index % width); // point(x,y)
+  int x = (int)(index % width),
+      y = (int)(index / width); // point(x,y)
 
   image[index] = dc_get_color(x, y, width, width, code);
 }
\ No newline at end of file
@@ -0,0 +1,59 @@
+// complex functions for zvm to_source expressions, cplx = (re, im)
+// cplx and cplx_(re, im) are defined per dialect in ZVm Dialect::prelude, which also
+// writes the L(x) float literals in the dialect's syntax, single precision everywhere
+
+float carg(cplx a) { return atan2(a.y, a.x); }
+cplx cmul(cplx a, cplx b) { return cplx_(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x); }
+cplx cmuli(cplx a) { return cplx_(-a.y, a.x); }
+cplx cdiv(cplx a, cplx b) {
+    float d = b.x * b.x + b.y * b.y;
+    return cplx_((a.x * b.x + a.y * b.y) / d, (a.y * b.x - a.x * b.y) / d);
+}
+cplx cc(cplx a, cplx b) { return cplx_(a.x, b.x); }
+cplx cabs(cplx a) { return cplx_(length(a), L(0.0)); }
+cplx cint(cplx a) { return cplx_(trunc(a.x), trunc(a.y)); }
+
+cplx cexp(cplx a) { return exp(a.x) * cplx_(cos(a.y), sin(a.y)); }
+cplx clog(cplx a) { return cplx_(log(length(a)), carg(a)); }
+cplx clog10(cplx a) { return clog(a) / log(L(10.0)); }
+cplx csqrt(cplx a) {
+    float r = sqrt(length(a)), t = L(0.5) * carg(a);
+    return r * cplx_(cos(t), sin(t));
+}
+cplx cpow(cplx a, cplx b) {
+    if (a.x == L(0.0) && a.y == L(0.0)) return cplx_(L(0.0), L(0.0));
+    return cexp(cmul(b, clog(a)));
+}
+cplx cpowi(cplx a, int n) {
+    cplx r = cplx_(L(1.0), L(0.0));
+    cplx b = n < 0 ? cdiv(r, a) : a;
+    int m = n < 0 ? -n : n;
+    for (int i = 0; i < m; i++) r = cmul(r, b);
+    return r;
+}
+
+cplx csin(cplx a) {
+    float ep = exp(a.y), em = exp(-a.y);
+    return cplx_(sin(a.x) * L(0.5) * (ep + em), cos(a.x) * L(0.5) * (ep - em));
+}
+cplx ccos(cplx a) {
+    float ep = exp(a.y), em = exp(-a.y);
+    return cplx_(cos(a.x) * L(0.5) * (ep + em), -sin(a.x) * L(0.5) * (ep - em));
+}
+cplx ctan(cplx a) {
+    float ep = exp(L(2.0) * a.y), em = exp(-L(2.0) * a.y);
+    float d = cos(L(2.0) * a.x) + L(0.5) * (ep + em);
+    return cplx_(sin(L(2.0) * a.x) / d, L(0.5) * (ep - em) / d);
+}
+cplx casin(cplx a) { // -i ln(sqrt(1 - a^2) + ia)
+    cplx l = clog(csqrt(cplx_(L(1.0), L(0.0)) - cmul(a, a)) + cmuli(a));
+    return cplx_(l.y, -l.x);
+}
+cplx cacos(cplx a) { // -i ln(i sqrt(1 - a^2) + a)
+    cplx l = clog(cmuli(csqrt(cplx_(L(1.0), L(0.0)) - cmul(a, a))) + a);
+    return cplx_(l.y, -l.x);
+}
+cplx catan(cplx a) { // (ln(1 + ia) - ln(1 - ia)) / 2i
+    cplx l = clog(cplx_(L(1.0), L(0.0)) + cmuli(a)) - clog(cplx_(L(1.0), L(0.0)) - cmuli(a));
+    return cplx_(L(0.5) * l.y, -L(0.5) * l.x);
+}
@@ -161,47 +161,94 @@ fn domain_coloring() {
 
 // zvm compiled code
 fn domain_coloring_zvm(expr: &str, w: u32) {
-    let zvm = ZVm::new(expr);
-    if zvm.ok() {
-        let code = zvm.get_code();
-        // zvm.walk();
-
-        // use nvidia platform=1, gpu device=0, check w/clinfo
-        let mut cl = Clw::new().with_platform(1).with_device(0);
-        cl.compile(include_str!("cl/dc_zvm.cl"), "domain_coloring");
-
-        let size = (w * w) as usize; // dc params(image, code)
-
-        let image = vec![0_u32; size];
-
-        // kernel parameters
-        let image_buffer = cl.out_buffer(&image, 0);
-        let (code_buffer, code_event) = cl.in_buffer(&code, 1); // END zvm code
-
-        // run & read image vec
-        cl.run(size);
-        cl.read(image_buffer, &image);
-        // release buffers
-        cl.free_buffer(image_buffer);
-        cl.free_buffer(code_buffer);
-        cl.free_event(code_event);
-
-        println!(
-            "lap dc - zvm for {}x{}={}: {:.0} ms",
-            w,
-            w,
-            size,
-            cl.lap_ns() as f32 / 1e6
-        );
-        write2file("dc_zvm.bin", &image);
-    } else {
-        panic!("z expression syntax error");
-    }
+    let zvm = ZVm::new(expr).unwrap_or_else(|err| panic!("z expression {}", err));
+    let code = zvm.get_code();
+    // zvm.walk();
+
+    // use nvidia platform=1, gpu device=0, check w/clinfo
+    let mut cl = Clw::new().with_platform(1).with_device(0);
+    cl.compile(
+        concat!(include_str!("cl/dc_color.cl"), include_str!("cl/dc_zvm.cl")),
+        "domain_coloring",
+    );
+
+    let size = (w * w) as usize; // dc params(image, code)
+
+    let image = vec![0_u32; size];
+
+    // kernel parameters
+    let image_buffer = cl.out_buffer(&image, 0);
+    let (code_buffer, code_event) = cl.in_buffer(&code, 1); // END zvm code
+
+    // run & read image vec
+    cl.run(size);
+    cl.read(image_buffer, &image);
+    // release buffers
+    cl.free_buffer(image_buffer);
+    cl.free_buffer(code_buffer);
+    cl.free_event(code_event);
+
+    println!(
+        "lap dc - zvm for {}x{}={}: {:.0} ms",
+        w,
+        w,
+        size,
+        cl.lap_ns() as f32 / 1e6
+    );
+    write2file("dc_zvm.bin", &image);
+}
+
+// zvm expression transpiled to OpenCL C
+fn domain_coloring_src(expr: &str, w: u32) {
+    let zvm = ZVm::new(expr).unwrap_or_else(|err| panic!("z expression {}", err));
+    let source = format!(
+        "{}\nfloat2 domain_color_func(float2 z) {{ return {}; }}\n{}{}",
+        Dialect::OpenCL.prelude(),
+        zvm.to_source(Dialect::OpenCL),
+        include_str!("cl/dc_color.cl"),
+        include_str!("cl/dc_src.cl")
+    );
+
+    // use nvidia platform=1, gpu device=0, check w/clinfo
+    let mut cl = Clw::new().with_platform(1).with_device(0);
+    cl.compile(&source, "domain_coloring");
+
+    let size = (w * w) as usize; // dc params(image)
+
+    let image = vec![0_u32; size];
+
+    // kernel parameters
+    let image_buffer = cl.out_buffer(&image, 0);
+
+    // run & read image vec
+    cl.run(size);
+    cl.read(image_buffer, &image);
+    cl.free_buffer(image_buffer);
+
+    println!(
+        "lap dc - src for {}x{}={}: {:.0} ms",
+        w,
+        w,
+        size,
+        cl.lap_ns() as f32 / 1e6
+    );
+    write2file("dc_src.bin", &image);
 }
 fn main() {
     // mandelbrot();
     // voronoi();
     // spherical_harmonics();
     // domain_coloring();
-    domain_coloring_zvm(PREDEF_FUNCS[18], 1024 * 2);
+
+    // ocl [z expression] [--zvm]: transpiled OpenCL C kernel, or the zvm bytecode interpreter kernel
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let expr = args
+        .iter()
+        .find(|a| !a.starts_with("--"))
+        .map_or(PREDEF_FUNCS[18], |a| a.as_str());
+    if args.iter().any(|a| a == "--zvm") {
+        domain_coloring_zvm(expr, 1024 * 2);
+    } else {
+        domain_coloring_src(expr, 1024 * 2);
+    }
 }
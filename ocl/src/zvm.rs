@@ -6,6 +6,7 @@
 
 use num::complex::Complex as complex;
 use std::f32::consts::{E, PI};
+use std::fmt;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 enum Symbols {
@@ -58,37 +59,124 @@ const PHI: f32 = 0.618033988_f32;
 pub type CF32 = complex<f32>;
 pub type ZFN = fn(CF32) -> CF32;
 
+fn int_exponent(e: CF32) -> Option<i32> {
+    // exponents evaluated with powi
+    if e.im == 0. && e.re.fract() == 0. && e.re.abs() < 1e6 {
+        Some(e.re as i32)
+    } else {
+        None
+    }
+}
+
+// shader language for to_source
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dialect {
+    OpenCL,
+    Glsl,
+    Metal,
+}
+
+impl Dialect {
+    fn literal(&self, x: f32) -> String {
+        match self {
+            Dialect::Glsl => format!("{:?}", x),
+            _ => format!("{:?}f", x),
+        }
+    }
+
+    fn complex(&self, re: f32, im: f32) -> String {
+        let (re, im) = (self.literal(re), self.literal(im));
+        match self {
+            Dialect::OpenCL => format!("(float2)({}, {})", re, im),
+            Dialect::Glsl => format!("vec2({}, {})", re, im),
+            Dialect::Metal => format!("float2({}, {})", re, im),
+        }
+    }
+
+    // complex function library used by the to_source expressions, cplx = (re, im)
+    // zfunc.h writes its float literals as L(0.5), emitted with literal()
+    pub fn prelude(&self) -> String {
+        let header = match self {
+            Dialect::OpenCL => "typedef float2 cplx;\n#define cplx_(x, y) ((float2)((x), (y)))\n",
+            Dialect::Glsl => "#define cplx vec2\n#define cplx_(x, y) vec2((x), (y))\n#define atan2(y, x) atan((y), (x))\n",
+            Dialect::Metal => "#include <metal_stdlib>\nusing namespace metal;\ntypedef float2 cplx;\n#define cplx_(x, y) float2((x), (y))\n",
+        };
+        let (mut lib, mut rest) = (String::from(header), include_str!("cl/zfunc.h"));
+        while let Some(at) = rest.find("L(") {
+            let end = at + rest[at..].find(')').unwrap();
+            match rest[at + 2..end].parse::<f32>() {
+                Ok(x) => lib += &format!("{}{}", &rest[..at], self.literal(x)),
+                Err(_) => lib += &rest[..=end],
+            }
+            rest = &rest[end + 1..];
+        }
+        lib + rest
+    }
+}
+
+// compile error: first offending token
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZError {
+    pub pos: usize, // char offset in source
+    pub expected: String,
+    pub found: String,
+}
+
+impl fmt::Display for ZError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "syntax error at {}: expected {}, found {}",
+            self.pos,
+            self.expected,
+            if self.found.is_empty() {
+                "end of expression".to_string()
+            } else {
+                format!("'{}'", self.found)
+            }
+        )
+    }
+}
+
+impl std::error::Error for ZError {}
+
 #[derive(Clone, Debug)]
 pub struct ZVm {
     pub source: String,
     ch: char,
     ixs: usize,
     sym: Symbols,
+    sym_pos: usize,
     ident: String,
     nval: f32,
-    err: bool,
+    err: Option<ZError>,
     code: Vec<u32>,
 }
 impl Default for ZVm {
     fn default() -> Self {
-        ZVm::new("")
+        ZVm::empty("") // evals to 0
     }
 }
 
 impl ZVm {
-    pub fn new(source: &str) -> Self {
-        let mut s = Self {
+    fn empty(source: &str) -> Self {
+        Self {
             source: String::from(source),
             ch: ' ',
             ixs: 0,
             sym: Symbols::SNULL,
+            sym_pos: 0,
             ident: "".to_string(),
             nval: 0_f32,
-            err: false,
-            code: vec![],
-        };
-        s.compile();
-        s
+            err: None,
+            code: vec![Symbols::END as u32],
+        }
+    }
+
+    pub fn new(source: &str) -> Result<Self, ZError> {
+        let mut s = Self::empty(source);
+        s.compile()?;
+        Ok(s)
     }
 
     fn u32_to_f32(u: u32) -> f32 {
@@ -106,7 +194,7 @@ impl ZVm {
 
     fn getch(&mut self) -> char {
         self.ch = '\0';
-        if self.ixs < self.source.len() {
+        if self.ixs < self.source.chars().count() {
             self.ch = self.source.chars().nth(self.ixs).unwrap();
             self.ixs += 1;
         }
@@ -122,6 +210,11 @@ impl ZVm {
         while self.ch != '\0' && self.ch <= ' ' {
             let _ = self.getch();
         }
+        self.sym_pos = if self.ch == '\0' {
+            self.source.chars().count()
+        } else {
+            self.ixs - 1
+        };
 
         // scan symbol
         if self.ch.is_alphabetic() {
@@ -144,7 +237,8 @@ impl ZVm {
                         unsafe { ::std::mem::transmute(Symbols::FSIN as u8 + index.unwrap() as u8) }
                 } else {
                     // error
-                    self.sym = Symbols::SNULL
+                    self.sym = Symbols::SNULL;
+                    self.error("function, constant, z or i");
                 }
             }
         } else if self.ch.is_digit(10) {
@@ -152,9 +246,22 @@ impl ZVm {
             while self.ch.is_digit(10) || self.ch == '.' || self.ch == 'e' || self.ch == 'E' {
                 self.ident.push(self.ch);
                 self.getch();
+                if (self.ch == '+' || self.ch == '-')
+                    && self.ident.ends_with(['e', 'E'])
+                {
+                    // 1e-3
+                    self.ident.push(self.ch);
+                    self.getch();
+                }
+            }
+            match self.ident.parse::<f32>() {
+                // atof
+                Ok(nval) => {
+                    self.sym = Symbols::NUMBER;
+                    self.nval = nval
+                }
+                Err(_) => self.error("number"),
             }
-            self.sym = Symbols::NUMBER;
-            self.nval = self.ident.parse::<f32>().unwrap(); // atof
         } else {
             self.sym = match self.ch {
                 '+' => Symbols::PLUS,
@@ -168,21 +275,47 @@ impl ZVm {
                 ',' => Symbols::COMMA,
                 _ => Symbols::SNULL,
             };
+            if self.ch != '\0' {
+                self.ident.push(self.ch)
+            }
+            if self.sym == Symbols::SNULL && self.ch != '\0' {
+                self.error("operator or operand")
+            }
             self.getch();
         }
         self.sym
     }
 
+    fn error(&mut self, expected: &str) {
+        // keep first error only
+        if self.err.is_none() {
+            self.err = Some(ZError {
+                pos: self.sym_pos,
+                expected: expected.to_string(),
+                found: self.ident.clone(),
+            })
+        }
+    }
+
+    fn sym_name(sym: Symbols) -> &'static str {
+        match sym {
+            Symbols::OPAREN => "'('",
+            Symbols::CPAREN => "')'",
+            Symbols::COMMA => "','",
+            _ => "operand",
+        }
+    }
+
     fn getsym_check(&mut self, chk_sym: Symbols) -> Symbols {
         if self.getsym() != chk_sym {
-            self.err = true;
+            self.error(Self::sym_name(chk_sym));
             self.sym = Symbols::SNULL
         }
         self.sym
     }
     fn sym_check(&mut self, chk_sym: Symbols) -> Symbols {
         if self.sym != chk_sym {
-            self.err = true;
+            self.error(Self::sym_name(chk_sym));
             self.sym = Symbols::SNULL
         } else {
             self.getsym();
@@ -192,7 +325,7 @@ impl ZVm {
 
     fn getsym_not_null(&mut self) -> Symbols {
         if self.getsym() == Symbols::SNULL {
-            self.err = true
+            self.error("operand")
         }
         self.sym
     }
@@ -210,7 +343,7 @@ impl ZVm {
     }
 
     fn c_e3(&mut self) {
-        if !self.err {
+        if self.err.is_none() {
             match self.sym {
                 Symbols::OPAREN => {
                     self.getsym();
@@ -256,11 +389,12 @@ impl ZVm {
                     self.gen(tsym);
                 }
                 Symbols::FC => {
+                    // c(e0, e0)
                     self.getsym_check(Symbols::OPAREN);
                     self.getsym();
-                    self.c_e3();
+                    self.c_e0();
                     self.sym_check(Symbols::COMMA);
-                    self.c_e3();
+                    self.c_e0();
                     self.sym_check(Symbols::CPAREN);
                     self.gen(Symbols::FC);
                 }
@@ -275,17 +409,15 @@ impl ZVm {
                     self.gen(Symbols::PUSHC);
                 }
 
-                Symbols::SNULL => {}
-
                 _ => {
-                    self.err = true;
+                    self.error("operand");
                 }
             }
         }
     }
 
     fn c_e2(&mut self) {
-        if !self.err {
+        if self.err.is_none() {
             self.c_e3();
 
             loop {
@@ -302,8 +434,9 @@ impl ZVm {
     }
 
     fn c_e1(&mut self) {
-        if !self.err {
+        if self.err.is_none() {
             self.c_e2();
+
             loop {
                 match self.sym {
                     Symbols::MULT => {
@@ -323,8 +456,9 @@ impl ZVm {
     }
 
     fn c_e0(&mut self) {
-        if !self.err {
+        if self.err.is_none() {
             self.c_e1();
+
             loop {
                 match self.sym {
                     Symbols::PLUS => {
@@ -343,19 +477,29 @@ impl ZVm {
         }
     }
 
-    pub fn compile(&mut self) {
-        self.err = false;
+    pub fn compile(&mut self) -> Result<(), ZError> {
+        self.err = None;
+        self.code.clear();
 
         self.getsym();
         self.c_e0();
+        if !self.ident.is_empty() {
+            self.error("operator or end of expression")
+        } // trailing tokens
 
-        if self.err {
+        if self.err.is_some() {
             self.code.clear()
         }
         self.gen(Symbols::END);
+
+        match &self.err {
+            Some(err) => Err(err.clone()),
+            None => Ok(()),
+        }
     }
+
     pub fn eval(&self, z: CF32) -> CF32 {
-        if self.err {
+        if self.err.is_some() {
             return CF32::new(0., 0.);
         }
 
@@ -401,11 +545,12 @@ impl ZVm {
                 Symbols::POWER => {
                     sp -= 1;
                     let zz = stack[sp];
-                    let zz = stack[sp - 1].powc(zz);
-                    stack[sp - 1] += zz;
+                    stack[sp - 1] = match int_exponent(zz) {
+                        Some(n) => stack[sp - 1].powi(n),
+                        None => stack[sp - 1].powc(zz),
+                    };
                 }
                 Symbols::NEG => {
-                    sp -= 1;
                     let zz = stack[sp - 1];
                     stack[sp - 1] = -zz;
                 }
@@ -420,7 +565,10 @@ impl ZVm {
                 Symbols::FLOG => stack[sp - 1] = stack[sp - 1].log(E),
                 Symbols::FLOG10 => stack[sp - 1] = stack[sp - 1].log(10.),
                 Symbols::FSQRT => stack[sp - 1] = stack[sp - 1].sqrt(),
-                Symbols::FABS => stack[sp - 1] = CF32::new(stack[sp - 1].arg(), 0.),
+                Symbols::FINT => {
+                    stack[sp - 1] = CF32::new(stack[sp - 1].re.trunc(), stack[sp - 1].im.trunc())
+                }
+                Symbols::FABS => stack[sp - 1] = CF32::new(stack[sp - 1].norm(), 0.),
                 Symbols::FC => {
                     sp -= 1;
                     stack[sp - 1] = CF32::new(stack[sp - 1].re, stack[sp].re)
@@ -441,8 +589,75 @@ impl ZVm {
     pub fn get_code(&self) -> Vec<u32> {
         self.code.clone()
     }
+
+    // native shader expression of z, e.g. cmul(z, csin(z)), needs dialect.prelude()
+    pub fn to_source(&self, dialect: Dialect) -> String {
+        let mut stack: Vec<(String, Option<f32>)> = vec![]; // expression, real constant value
+        let mut pc = 0;
+
+        loop {
+            let sym = Self::u32_2_sym(self.code[pc]);
+            let expr = match sym {
+                Symbols::PUSHC => {
+                    pc += 1;
+                    let c = Self::u32_to_f32(self.code[pc]);
+                    stack.push((dialect.complex(c, 0.), Some(c)));
+                    pc += 1;
+                    continue;
+                }
+                Symbols::PUSHZ => "z".to_string(),
+                Symbols::PUSHI => dialect.complex(0., 1.),
+                Symbols::PLUS
+                | Symbols::MINUS
+                | Symbols::MULT
+                | Symbols::DIV
+                | Symbols::POWER
+                | Symbols::FC => {
+                    let (b, bc) = stack.pop().unwrap();
+                    let (a, _) = stack.pop().unwrap();
+                    match sym {
+                        Symbols::PLUS => format!("({} + {})", a, b),
+                        Symbols::MINUS => format!("({} - {})", a, b),
+                        Symbols::MULT => format!("cmul({}, {})", a, b),
+                        Symbols::DIV => format!("cdiv({}, {})", a, b),
+                        Symbols::FC => format!("cc({}, {})", a, b),
+                        _ => match bc.and_then(|c| int_exponent(CF32::new(c, 0.))) {
+                            Some(n) => format!("cpowi({}, {})", a, n),
+                            None => format!("cpow({}, {})", a, b),
+                        },
+                    }
+                }
+                Symbols::END => break,
+                Symbols::NEG => format!("(-{})", stack.pop().unwrap().0),
+                _ => {
+                    let (a, _) = stack.pop().unwrap();
+                    let func = match sym {
+                        Symbols::FSIN => "csin",
+                        Symbols::FCOS => "ccos",
+                        Symbols::FTAN => "ctan",
+                        Symbols::FASIN => "casin",
+                        Symbols::FACOS => "cacos",
+                        Symbols::FATAN => "catan",
+                        Symbols::FEXP => "cexp",
+                        Symbols::FLOG => "clog",
+                        Symbols::FLOG10 => "clog10",
+                        Symbols::FSQRT => "csqrt",
+                        Symbols::FINT => "cint",
+                        Symbols::FABS => "cabs",
+                        _ => "",
+                    };
+                    format!("{}({})", func, a)
+                }
+            };
+            stack.push((expr, None));
+            pc += 1;
+        }
+        stack
+            .pop()
+            .map_or_else(|| dialect.complex(0., 0.), |(expr, _)| expr)
+    }
     pub fn ok(&self) -> bool {
-        !self.err
+        self.err.is_none()
     }
 
     #[allow(dead_code)]
@@ -499,3 +714,281 @@ pub const PREDEF_FUNCS: [&str; 19] = [
     "(z^2)-0.75-c(0,0.2)",
     "z * sin( c(1,1)/cos(3/z) + tan(1/z+1) )",
 ];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn err(source: &str) -> ZError {
+        ZVm::new(source).unwrap_err()
+    }
+
+    #[test]
+    fn compile_errors() {
+        assert!(ZVm::new("z * sin( c(1,1)/cos(3/z) + tan(1/z+1) )").is_ok());
+        assert!(ZVm::new("1e-3 * z").is_ok());
+        assert_eq!(
+            ZVm::new("c(1+2, -z*2)").unwrap().eval(CF32::new(2., 0.)),
+            CF32::new(3., -4.)
+        );
+
+        assert_eq!(
+            err("z + foo(z)"),
+            ZError {
+                pos: 4,
+                expected: "function, constant, z or i".to_string(),
+                found: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            err("2 * 1.2.3"),
+            ZError {
+                pos: 4,
+                expected: "number".to_string(),
+                found: "1.2.3".to_string()
+            }
+        );
+        assert_eq!(
+            err("sin(z"),
+            ZError {
+                pos: 5,
+                expected: "')'".to_string(),
+                found: "".to_string()
+            }
+        );
+        assert_eq!(
+            err("sin z"),
+            ZError {
+                pos: 4,
+                expected: "'('".to_string(),
+                found: "z".to_string()
+            }
+        );
+        assert_eq!(
+            err("c(1 2)"),
+            ZError {
+                pos: 4,
+                expected: "','".to_string(),
+                found: "2".to_string()
+            }
+        );
+        assert_eq!(
+            err("z +"),
+            ZError {
+                pos: 3,
+                expected: "operand".to_string(),
+                found: "".to_string()
+            }
+        );
+        assert_eq!(
+            err("z $ 2"),
+            ZError {
+                pos: 2,
+                expected: "operator or operand".to_string(),
+                found: "$".to_string()
+            }
+        );
+        assert_eq!(
+            err("sin(z))"),
+            ZError {
+                pos: 6,
+                expected: "operator or end of expression".to_string(),
+                found: ")".to_string()
+            }
+        );
+        assert_eq!(
+            err(""),
+            ZError {
+                pos: 0,
+                expected: "operand".to_string(),
+                found: "".to_string()
+            }
+        );
+
+        assert_eq!(
+            format!("{}", err("sin(z")),
+            "syntax error at 5: expected ')', found end of expression"
+        );
+    }
+
+    #[test]
+    fn eval() {
+        // z^n replaces z, -x keeps the stack depth, abs is the modulus, int truncates
+        let z = CF32::new(0.5, -1.25);
+        let eval = |source: &str| ZVm::new(source).unwrap().eval(z);
+        assert!((eval("z^3") - z * z * z).norm() < 1e-5);
+        assert!((eval("z^c(0.5,1)") - z.powc(CF32::new(0.5, 1.))).norm() < 1e-5);
+        assert_eq!(eval("-z + 2"), CF32::new(1.5, 1.25));
+        assert_eq!(eval("abs(c(3,4))"), CF32::new(5., 0.));
+        assert_eq!(eval("int(z*3)"), CF32::new(1., -3.));
+    }
+
+    // evaluates to_source text with the zfunc.h formulas
+    struct Src<'a> {
+        s: &'a [u8],
+        i: usize,
+        z: CF32,
+    }
+
+    impl<'a> Src<'a> {
+        fn skip(&mut self, t: &str) -> bool {
+            while self.i < self.s.len() && self.s[self.i] == b' ' {
+                self.i += 1
+            }
+            let ok = self.s[self.i..].starts_with(t.as_bytes());
+            if ok {
+                self.i += t.len()
+            }
+            ok
+        }
+
+        fn ident(&mut self) -> String {
+            let st = self.i;
+            while self.i < self.s.len()
+                && (self.s[self.i].is_ascii_alphanumeric() || b".-".contains(&self.s[self.i]))
+            {
+                self.i += 1
+            }
+            String::from_utf8(self.s[st..self.i].to_vec()).unwrap()
+        }
+
+        fn expr(&mut self) -> CF32 {
+            if self.skip("(float2)(") {
+                return self.call("float2");
+            }
+            if self.skip("(-") {
+                let a = -self.expr();
+                self.skip(")");
+                return a;
+            }
+            if self.skip("(") {
+                let a = self.expr();
+                let b = if self.skip("+") {
+                    a + self.expr()
+                } else {
+                    self.skip("-");
+                    a - self.expr()
+                };
+                self.skip(")");
+                return b;
+            }
+            let id = self.ident();
+            if self.skip("(") {
+                self.call(&id)
+            } else if id == "z" {
+                self.z
+            } else {
+                CF32::new(id.trim_end_matches('f').parse().unwrap(), 0.)
+            }
+        }
+
+        fn call(&mut self, f: &str) -> CF32 {
+            let a = self.expr();
+            let b = if self.skip(",") { self.expr() } else { a };
+            self.skip(")");
+
+            let one = CF32::new(1., 0.);
+            let i = CF32::new(0., 1.);
+            let sh = |y: f32| 0.5 * (y.exp() - (-y).exp());
+            let ch = |y: f32| 0.5 * (y.exp() + (-y).exp());
+            let exp = |a: CF32| a.re.exp() * CF32::new(a.im.cos(), a.im.sin());
+            let log = |a: CF32| CF32::new(a.norm().ln(), a.im.atan2(a.re));
+            let sqrt = |a: CF32| {
+                let (r, t) = (a.norm().sqrt(), 0.5 * a.im.atan2(a.re));
+                r * CF32::new(t.cos(), t.sin())
+            };
+            match f {
+                "float2" | "vec2" => CF32::new(a.re, b.re),
+                "cmul" => CF32::new(a.re * b.re - a.im * b.im, a.re * b.im + a.im * b.re),
+                "cdiv" => {
+                    let d = b.re * b.re + b.im * b.im;
+                    CF32::new(
+                        (a.re * b.re + a.im * b.im) / d,
+                        (a.im * b.re - a.re * b.im) / d,
+                    )
+                }
+                "cc" => CF32::new(a.re, b.re),
+                "cabs" => CF32::new(a.norm(), 0.),
+                "cint" => CF32::new(a.re.trunc(), a.im.trunc()),
+                "cexp" => exp(a),
+                "clog" => log(a),
+                "clog10" => log(a) / 10f32.ln(),
+                "csqrt" => sqrt(a),
+                "cpow" => exp(b * log(a)),
+                "cpowi" => {
+                    let n = b.re as i32;
+                    let c = if n < 0 { one / a } else { a };
+                    (0..n.abs()).fold(one, |r, _| r * c)
+                }
+                "csin" => CF32::new(a.re.sin() * ch(a.im), a.re.cos() * sh(a.im)),
+                "ccos" => CF32::new(a.re.cos() * ch(a.im), -a.re.sin() * sh(a.im)),
+                "ctan" => {
+                    let d = (2. * a.re).cos() + ch(2. * a.im);
+                    CF32::new((2. * a.re).sin() / d, sh(2. * a.im) / d)
+                }
+                "casin" => -i * log(sqrt(one - a * a) + i * a),
+                "cacos" => -i * log(i * sqrt(one - a * a) + a),
+                "catan" => (log(one + i * a) - log(one - i * a)) / (2. * i),
+                _ => panic!("unknown function {}", f),
+            }
+        }
+    }
+
+    #[test]
+    fn to_source() {
+        let zvm = ZVm::new("c(1,2)*sin(z)^2 - -z/3").unwrap();
+        assert_eq!(zvm.to_source(Dialect::OpenCL), "(cmul(cc((float2)(1.0f, 0.0f), (float2)(2.0f, 0.0f)), cpowi(csin(z), 2)) - cdiv((-z), (float2)(3.0f, 0.0f)))");
+        assert!(zvm.to_source(Dialect::Glsl).contains("vec2(3.0, 0.0)"));
+        assert!(zvm.to_source(Dialect::Metal).contains("float2(3.0f, 0.0f)"));
+        assert!(Dialect::Glsl
+            .prelude()
+            .contains("cplx cpowi(cplx a, int n)"));
+
+        // single precision literals only, no fp64 needed
+        for dialect in [Dialect::OpenCL, Dialect::Glsl, Dialect::Metal] {
+            let prelude = dialect.prelude();
+            assert!(!prelude.contains("L(0") && !prelude.contains("L(1"));
+            assert!(prelude.contains(&format!("log({})", dialect.literal(10.))));
+            let b = prelude.as_bytes();
+            for (k, _) in prelude.match_indices('.') {
+                if k > 0 && b[k - 1].is_ascii_digit() && b[k + 1].is_ascii_digit() {
+                    let end = k + 1 + b[k + 1..].iter().take_while(|c| c.is_ascii_digit()).count();
+                    assert_eq!(b[end] == b'f', dialect != Dialect::Glsl, "{:?} {}", dialect, &prelude[k - 1..end + 1]);
+                }
+            }
+        }
+
+        for expr in PREDEF_FUNCS
+            .iter()
+            .chain(["-z^-2 + abs(z) + exp(z)^z", "log10(int(z*3))"].iter())
+        {
+            let zvm = ZVm::new(expr).unwrap();
+            for dialect in [Dialect::OpenCL, Dialect::Glsl, Dialect::Metal] {
+                let src = zvm.to_source(dialect);
+                for k in 0..64 {
+                    let z = CF32::new((k % 8) as f32 * 0.37 - 1.3, (k / 8) as f32 * 0.41 - 1.4);
+                    let (v, r) = (
+                        zvm.eval(z),
+                        Src {
+                            s: src.as_bytes(),
+                            i: 0,
+                            z,
+                        }
+                        .expr(),
+                    );
+                    if v.is_finite() && v.norm() < 1e4 {
+                        assert!(
+                            (v - r).norm() <= 1e-3 * (1. + v.norm()),
+                            "{} {:?} at {}: {} != {}",
+                            expr,
+                            dialect,
+                            z,
+                            r,
+                            v
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,357 @@
+/*
+	exact voronoi diagram, euclidean metric
+
+	bowyer-watson delaunay triangulation: sites are inserted one by one (grid order, short walks), the
+	triangle holding the site is found by walking, the triangles whose circumcircle holds it are removed
+	(flood fill from there) and the hole is filled with a fan. orientation & incircle are exact in i128 on
+	the integer site coordinates, sites stay below MAX_COORD, larger sites are an error.
+	the super triangle is symbolic, its vertices R d_k with R -> infinity: predicates on its vertices are
+	polynomials in R, their sign that of the highest nonzero coefficient. no site circumcircle holds a super
+	vertex, so every delaunay triangle of the sites is found, also between nearly collinear hull sites.
+	the voronoi cell of a site is the image rectangle clipped by the bisectors with its delaunay neighbours,
+	the site edges of every triangle, also those with a super vertex: collinear sites have no triangle of their own.
+
+	geometry is in image coordinates, pixel (x, y) covers [x, x+1] x [y, y+1], sites sit at pixel centers
+*/
+
+use super::*;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+
+const MAX_COORD : i64 = 1 << 28; // |incircle| < 3 2^118, also its coefficients in R
+const NONE : usize = usize::MAX;
+const SUPER : [Vertex; 3] = [(-1, -1), (1, 0), (0, 1)]; // super vertex directions, counterclockwise around 0
+
+type Vertex = (i64, i64);
+type Poly = [i128; 5]; // c0 + c1 R + .. + c4 R^4
+
+fn orient(a : Vertex, b : Vertex, c : Vertex) -> i128 {
+	(b.0 - a.0) as i128 * (c.1 - a.1) as i128 - (b.1 - a.1) as i128 * (c.0 - a.0) as i128
+}
+
+// > 0 when d is inside the circle through a, b, c (counterclockwise, y up)
+fn incircle(a : Vertex, b : Vertex, c : Vertex, d : Vertex) -> i128 {
+	let (adx, ady, bdx, bdy, cdx, cdy) = ((a.0 - d.0) as i128, (a.1 - d.1) as i128, (b.0 - d.0) as i128, (b.1 - d.1) as i128, (c.0 - d.0) as i128, (c.1 - d.1) as i128);
+	(adx * adx + ady * ady) * (bdx * cdy - cdx * bdy) + (bdx * bdx + bdy * bdy) * (cdx * ady - adx * cdy) + (cdx * cdx + cdy * cdy) * (adx * bdy - bdx * ady)
+}
+
+fn poly_sub(p : &Poly, q : &Poly) -> Poly { [p[0] - q[0], p[1] - q[1], p[2] - q[2], p[3] - q[3], p[4] - q[4]] }
+
+fn poly_mul(p : &Poly, q : &Poly) -> Poly { // degrees add up to 4 at most
+	let mut r = [0; 5];
+	for i in 0..5 {
+		for j in 0..5 - i { r[i + j] += p[i] * q[j] }
+	}
+	r
+}
+
+fn poly_sign(p : &Poly) -> i128 { p.iter().rev().find(|&&c| c != 0).map_or(0, |c| c.signum()) }
+
+// x & y polynomials in R
+type SymVertex = [Poly; 2];
+
+fn sym_orient(a : &SymVertex, b : &SymVertex, c : &SymVertex) -> i128 {
+	let (bx, by, cx, cy) = (poly_sub(&b[0], &a[0]), poly_sub(&b[1], &a[1]), poly_sub(&c[0], &a[0]), poly_sub(&c[1], &a[1]));
+	poly_sign(&poly_sub(&poly_mul(&bx, &cy), &poly_mul(&by, &cx)))
+}
+
+fn sym_incircle(a : &SymVertex, b : &SymVertex, c : &SymVertex, d : &SymVertex) -> i128 {
+	let [adx, ady, bdx, bdy, cdx, cdy] = [poly_sub(&a[0], &d[0]), poly_sub(&a[1], &d[1]), poly_sub(&b[0], &d[0]), poly_sub(&b[1], &d[1]), poly_sub(&c[0], &d[0]), poly_sub(&c[1], &d[1])];
+	let lift = |x : &Poly, y : &Poly| { let (xx, yy) = (poly_mul(x, x), poly_mul(y, y)); [xx[0] + yy[0], xx[1] + yy[1], xx[2] + yy[2], 0, 0] };
+	let cross = |x1 : &Poly, y1 : &Poly, x2 : &Poly, y2 : &Poly| poly_sub(&poly_mul(x1, y2), &poly_mul(x2, y1));
+	let terms = [poly_mul(&lift(&adx, &ady), &cross(&bdx, &bdy, &cdx, &cdy)), poly_mul(&lift(&bdx, &bdy), &cross(&cdx, &cdy, &adx, &ady)), poly_mul(&lift(&cdx, &cdy), &cross(&adx, &ady, &bdx, &bdy))];
+	let mut sum = [0; 5];
+	for t in terms.iter() { for k in 0..5 { sum[k] += t[k] } }
+	poly_sign(&sum)
+}
+
+// triangle vertices counterclockwise, nbr[k] across the edge opposite v[k]
+#[derive(Clone, Copy, Debug)]
+struct Triangle {
+	v 	  : [usize; 3],
+	nbr   : [usize; 3],
+	alive : bool,
+}
+
+struct Triangulation {
+	verts : Vec<Vertex>, // sites then the 3 super vertex directions
+	n 	  : usize, // site count
+	tris  : Vec<Triangle>,
+	last  : usize, // walk start
+}
+
+impl Triangulation {
+	fn new(mut verts : Vec<Vertex>) -> io::Result<Self> {
+		if verts.iter().any(|v| v.0.abs().max(v.1.abs()) >= MAX_COORD) {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("site coordinates over {} are too large for exact predicates", MAX_COORD)))
+		}
+		let n = verts.len();
+		verts.extend(SUPER.iter());
+		let tris = vec![Triangle { v : [n, n + 1, n + 2], nbr : [NONE; 3], alive : true }];
+		Ok(Self { verts, n, tris, last : 0 })
+	}
+
+	fn sym(&self, v : usize) -> SymVertex {
+		let (x, y) = (self.verts[v].0 as i128, self.verts[v].1 as i128);
+		if v < self.n { [[x, 0, 0, 0, 0], [y, 0, 0, 0, 0]] } else { [[0, x, 0, 0, 0], [0, y, 0, 0, 0]] }
+	}
+
+	fn orient(&self, a : usize, b : usize, p : usize) -> i128 {
+		if a < self.n && b < self.n { return orient(self.verts[a], self.verts[b], self.verts[p]) }
+		sym_orient(&self.sym(a), &self.sym(b), &self.sym(p))
+	}
+
+	fn contains(&self, t : usize, p : usize) -> Option<usize> { // None: inside or on, else the edge to cross
+		let v = self.tris[t].v;
+		(0..3).find(|&k| self.orient(v[(k + 1) % 3], v[(k + 2) % 3], p) < 0)
+	}
+
+	fn locate(&self, p : usize) -> usize {
+		let mut t = self.last;
+		while let Some(k) = self.contains(t, p) { t = self.tris[t].nbr[k] }
+		t
+	}
+
+	fn in_circle(&self, t : usize, p : usize) -> bool {
+		let v = self.tris[t].v;
+		if v.iter().all(|&v| v < self.n) { return incircle(self.verts[v[0]], self.verts[v[1]], self.verts[v[2]], self.verts[p]) > 0 }
+		sym_incircle(&self.sym(v[0]), &self.sym(v[1]), &self.sym(v[2]), &self.sym(p)) > 0
+	}
+
+	fn insert(&mut self, s : usize) {
+		let start = self.locate(s);
+
+		// cavity: triangles whose circumcircle holds site s, connected
+		let mut cavity = vec![start];
+		self.tris[start].alive = false;
+		let mut k = 0;
+		while k < cavity.len() {
+			let nbr = self.tris[cavity[k]].nbr;
+			for &n in nbr.iter() {
+				if n != NONE && self.tris[n].alive && self.in_circle(n, s) {
+					self.tris[n].alive = false;
+					cavity.push(n);
+				}
+			}
+			k += 1;
+		}
+
+		// fan from s to the cavity border, border edges keep their orientation
+		let mut by_start = HashMap::new();
+		for &t in cavity.iter() {
+			let tri = self.tris[t];
+			for k in 0..3 {
+				let outer = tri.nbr[k];
+				if outer != NONE && !self.tris[outer].alive { continue } // inside the cavity
+				let (a, b) = (tri.v[(k + 1) % 3], tri.v[(k + 2) % 3]);
+				let new = self.tris.len();
+				self.tris.push(Triangle { v : [a, b, s], nbr : [NONE, NONE, outer], alive : true });
+				if outer != NONE {
+					let back = self.tris[outer].nbr.iter().position(|&n| n == t).expect("neighbours should be symmetric");
+					self.tris[outer].nbr[back] = new;
+				}
+				by_start.insert(a, new);
+			}
+		}
+		for (_, &t) in by_start.iter() {
+			let b = self.tris[t].v[1];
+			let next = by_start[&b]; // [b, c, s], shares the edge b-s
+			self.tris[t].nbr[0] = next;
+			self.tris[next].nbr[1] = t;
+		}
+		self.last = self.tris.len() - 1;
+	}
+}
+
+// site indices everywhere, cells indexed by site
+#[derive(Clone, Debug, Default)]
+pub struct Diagram {
+	pub triangles 	: Vec<[usize; 3]>, // delaunay, counterclockwise with y up
+	pub neighbours 	: Vec<Vec<usize>>, // delaunay adjacency, sorted
+	pub cells 		: Vec<Vec<(f64, f64)>>, // polygons clipped to the image, empty for duplicate sites
+}
+
+// keeps the side of the line a x + b y <= c
+fn clip(poly : &[(f64, f64)], a : f64, b : f64, c : f64) -> Vec<(f64, f64)> {
+	let side = |p : &(f64, f64)| a * p.0 + b * p.1 - c;
+	let mut out = Vec::with_capacity(poly.len() + 1);
+	for k in 0..poly.len() {
+		let (p, q) = (poly[k], poly[(k + 1) % poly.len()]);
+		let (sp, sq) = (side(&p), side(&q));
+		if sp <= 0. { out.push(p) }
+		if (sp < 0. && sq > 0.) || (sp > 0. && sq < 0.) {
+			let t = sp / (sp - sq);
+			out.push((p.0 + t * (q.0 - p.0), p.1 + t * (q.1 - p.1)));
+		}
+	}
+	out
+}
+
+#[cfg(test)]
+pub fn polygon_area(poly : &[(f64, f64)]) -> f64 {
+	(0..poly.len()).map(|k| { let (p, q) = (poly[k], poly[(k + 1) % poly.len()]); p.0 * q.1 - q.0 * p.1 }).sum::<f64>() / 2.
+}
+
+impl Voronoi {
+	pub fn diagram(&self) -> io::Result<Diagram> {
+		let n = self.points.len();
+
+		// duplicates share the first site, as in the raster where the lowest index wins ties
+		let mut first = HashMap::new();
+		let unique : Vec<usize> = (0..n).filter(|&it| *first.entry((self.points[it].x, self.points[it].y)).or_insert(it) == it).collect();
+
+		let verts = self.points.iter().map(|p| (p.x as i64, p.y as i64)).collect();
+		let mut tr = Triangulation::new(verts)?;
+		let grid = self.grid(); // insertion in grid cell order, rows alternate direction
+		let mut order = unique.clone();
+		order.sort_by_key(|&it| {
+			let p = &self.points[it];
+			let (cx, cy) = (p.x / grid.cell(), p.y / grid.cell());
+			(cy, if cy % 2 == 0 { cx } else { u32::MAX - cx })
+		});
+		for &it in order.iter() { tr.insert(it) }
+
+		let triangles : Vec<[usize; 3]> = tr.tris.iter().filter(|t| t.alive && t.v.iter().all(|&v| v < n)).map(|t| t.v).collect();
+		let mut neighbours = vec![vec![]; n];
+		for t in tr.tris.iter().filter(|t| t.alive) {
+			for k in 0..3 {
+				let (a, b) = (t.v[k], t.v[(k + 1) % 3]);
+				if a < n && b < n { neighbours[a].push(b); neighbours[b].push(a) }
+			}
+		}
+		for nb in neighbours.iter_mut() { nb.sort_unstable(); nb.dedup() }
+
+		let rect = vec![(0., 0.), (self.w as f64, 0.), (self.w as f64, self.h as f64), (0., self.h as f64)];
+		let center = |it : usize| (self.points[it].x as f64 + 0.5, self.points[it].y as f64 + 0.5);
+		let cells = (0..n).into_par_iter().map(|it| {
+			if first[&(self.points[it].x, self.points[it].y)] != it { return vec![] }
+			let s = center(it);
+			neighbours[it].iter().fold(rect.clone(), |cell, &nb| { // |p - s|² <= |p - t|²
+				let t = center(nb);
+				clip(&cell, 2. * (t.0 - s.0), 2. * (t.1 - s.1), t.0 * t.0 + t.1 * t.1 - s.0 * s.0 - s.1 * s.1)
+			})
+		}).collect();
+
+		Ok(Diagram { triangles, neighbours, cells })
+	}
+
+	// cells filled with the site colors, site dots, delaunay edges if asked
+	pub fn write_svg(&self, name : &str, d : &Diagram, delaunay : bool) -> io::Result<()> {
+		let mut svg = String::new();
+		let _ = writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">", self.w, self.h, self.w, self.h);
+		for (it, cell) in d.cells.iter().enumerate().filter(|(_, c)| c.len() >= 3) {
+			let points : Vec<String> = cell.iter().map(|p| format!("{:.3},{:.3}", p.0, p.1)).collect();
+			let _ = writeln!(svg, "<polygon points=\"{}\" fill=\"{}\" stroke=\"none\"/>", points.join(" "), format_color(self.points[it].color));
+		}
+		if delaunay {
+			let _ = write!(svg, "<path fill=\"none\" stroke=\"black\" stroke-width=\"0.5\" d=\"");
+			for (a, nb) in d.neighbours.iter().enumerate() {
+				for &b in nb.iter().filter(|&&b| b > a) {
+					let (p, q) = (&self.points[a], &self.points[b]);
+					let _ = write!(svg, "M{}.5 {}.5L{}.5 {}.5", p.x, p.y, q.x, q.y);
+				}
+			}
+			let _ = writeln!(svg, "\"/>");
+		}
+		for p in self.points.iter() {
+			let _ = writeln!(svg, "<circle cx=\"{}.5\" cy=\"{}.5\" r=\"{}\"/>", p.x, p.y, CENTER_RADIUS);
+		}
+		svg += "</svg>\n";
+		fs::write(name, svg)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn hull_area(points : &[Point]) -> f64 { // monotone chain
+		let mut p : Vec<(i64, i64)> = points.iter().map(|p| (p.x as i64, p.y as i64)).collect();
+		p.sort_unstable();
+		p.dedup();
+		let mut hull : Vec<(i64, i64)> = vec![];
+		for pass in 0..2 {
+			let start = hull.len();
+			for &q in p.iter() {
+				while hull.len() >= start + 2 && orient(hull[hull.len() - 2], hull[hull.len() - 1], q) <= 0 { hull.pop(); }
+				hull.push(q);
+			}
+			hull.pop();
+			if pass == 0 { p.reverse() }
+		}
+		polygon_area(&hull.iter().map(|&(x, y)| (x as f64, y as f64)).collect::<Vec<_>>())
+	}
+
+	// cells tile the image and hold the pixels of their raster cell
+	fn check_cells(v : &Voronoi, d : &Diagram) {
+		let cells : f64 = d.cells.iter().map(|c| polygon_area(c)).sum();
+		assert!((cells - (v.w * v.h) as f64).abs() < 1e-6, "{}", cells);
+		for (index, n) in v.nearest_all(true).iter().enumerate() {
+			if n.f2 - n.f1 < 1e-6 { continue } // pixel center on an edge
+			let (x, y) = ((index as u32 % v.w) as f64 + 0.5, (index as u32 / v.w) as f64 + 0.5);
+			let cell = &d.cells[n.site];
+			assert!((0..cell.len()).all(|k| { let (p, q) = (cell[k], cell[(k + 1) % cell.len()]); (q.0 - p.0) * (y - p.1) - (q.1 - p.1) * (x - p.0) >= -1e-9 }));
+		}
+	}
+
+	#[test]
+	fn delaunay() {
+		let square = Voronoi::from_points(10, 10, vec![Point::new(1, 1, 0), Point::new(8, 1, 0), Point::new(8, 8, 0), Point::new(1, 8, 0), Point::new(1, 1, 0)]);
+		let d = square.diagram().unwrap();
+		assert_eq!(d.triangles.len(), 2); // cocircular
+		assert!(d.cells[4].is_empty());
+		assert!((polygon_area(&d.cells[0]) - 25.).abs() < 1e-9);
+
+		// sites on a small grid: many cocircular & collinear sites
+		let mut points : Vec<Point> = (0..64).map(|k| Point::new(k % 8 * 5 + 3, k / 8 * 4 + 2, site_color(k as usize))).collect();
+		points.extend(Voronoi::seeded(60, 40, 200, 3).points().iter().cloned());
+		for v in [Voronoi::from_points(60, 40, points), Voronoi::seeded(300, 200, 1000, 9)].iter() {
+			let d = v.diagram().unwrap();
+			let vert = |it : usize| (v.points[it].x as i64, v.points[it].y as i64);
+			for t in d.triangles.iter() {
+				assert!(orient(vert(t[0]), vert(t[1]), vert(t[2])) > 0);
+				assert!(!d.cells.iter().enumerate().any(|(it, c)| !c.is_empty() && incircle(vert(t[0]), vert(t[1]), vert(t[2]), vert(it)) > 0), "not delaunay");
+			}
+			let area : f64 = d.triangles.iter().map(|t| orient(vert(t[0]), vert(t[1]), vert(t[2])) as f64 / 2.).sum();
+			assert_eq!(area, hull_area(&v.points));
+			check_cells(v, &d);
+		}
+
+		// no triangle between sites: two sites, collinear sites with a duplicate
+		let two = Voronoi::from_points(10, 10, vec![Point::new(2, 5, 0), Point::new(7, 5, 0)]);
+		let d = two.diagram().unwrap();
+		assert!(d.triangles.is_empty() && d.neighbours == [vec![1], vec![0]]);
+		assert_eq!((polygon_area(&d.cells[0]), polygon_area(&d.cells[1])), (50., 50.));
+		check_cells(&two, &d);
+		let line = Voronoi::from_points(40, 30, [(1, 1), (7, 5), (4, 3), (25, 17), (37, 25), (7, 5)].iter().map(|&(x, y)| Point::new(x, y, 0)).collect());
+		let d = line.diagram().unwrap();
+		assert!(d.triangles.is_empty() && d.cells[5].is_empty());
+		assert_eq!(d.neighbours[..5], [vec![2], vec![2, 3], vec![0, 1], vec![1, 4], vec![3]]);
+		check_cells(&line, &d);
+
+		// nearly collinear hull sites, huge circumcircles
+		let flat = Voronoi::from_points(10, 10, vec![Point::new(0, 0, 0), Point::new(1000, 1, 0), Point::new(2000, 0, 0)]);
+		let d = flat.diagram().unwrap();
+		assert_eq!(d.triangles.len(), 1);
+		assert_eq!(d.neighbours, [vec![1, 2], vec![0, 2], vec![0, 1]]);
+		let mut points = flat.points().to_vec();
+		points.push(Point::new(1000, 2000, 0));
+		let kite = Voronoi::from_points(10, 10, points);
+		let d = kite.diagram().unwrap();
+		let vert = |it : usize| (kite.points[it].x as i64, kite.points[it].y as i64);
+		let area : f64 = d.triangles.iter().map(|t| orient(vert(t[0]), vert(t[1]), vert(t[2])) as f64 / 2.).sum();
+		assert_eq!(area, hull_area(&kite.points));
+		assert!(d.triangles.iter().any(|t| { let mut t = *t; t.sort_unstable(); t == [0, 1, 2] }));
+
+		assert!(Voronoi::from_points(10, 10, vec![Point::new(300_000_000, 5, 0), Point::new(1, 1, 0)]).diagram().is_err());
+
+		let name = std::env::temp_dir().join("voronoi_test.svg");
+		let v = Voronoi::seeded(80, 60, 30, 1);
+		v.write_svg(name.to_str().unwrap(), &v.diagram().unwrap(), true).unwrap();
+		let svg = fs::read_to_string(&name).unwrap();
+		assert_eq!(svg.matches("<polygon").count(), 30);
+		assert!(svg.contains(&format!("fill=\"{}\"", format_color(v.points[0].color))));
+		let _ = fs::remove_file(name);
+	}
+}
@@ -0,0 +1,184 @@
+/*
+	lloyd relaxation towards a centroidal voronoi tessellation
+
+	every iteration moves each site to the centroid of its cell, pixels weighted by an optional density
+	(weighted voronoi stippling: dark image areas attract sites). sites are whole pixels, so the relaxation
+	runs on a grid 'scale' times finer with the density sampled per image pixel: a centroid pull under half
+	a fine pixel is lost, cells of a few pixels barely follow a density at scale 1.
+	a tessellation then stops moving exactly, or hops by a fine pixel, the iteration limit ends those.
+	converged when no site moves farther than the tolerance, in image pixels.
+*/
+
+use super::*;
+use image::imageops::FilterType;
+use std::fmt::Write as _;
+use std::fs;
+
+const DOT_RADIUS : f64 = 1.5; // stipple dots
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lloyd {
+	pub max_iters 	: u32,
+	pub tolerance 	: f64, // pixels
+	pub scale 		: u32, // fine grid pixels per image pixel
+	pub density 	: Option<Vec<f32>>, // per pixel weight, uniform if None
+	pub svg_prefix 	: Option<String>, // dots of every iteration to prefix_000.svg..
+}
+
+impl Lloyd {
+	pub fn new(max_iters : u32, tolerance : f64) -> Self { Self { max_iters, tolerance, scale : 1, density : None, svg_prefix : None } }
+
+	pub fn with_scale(mut self, scale : u32) -> Self {
+		self.scale = scale.max(1);
+		self
+	}
+
+	pub fn with_density(mut self, density : Vec<f32>) -> Self {
+		self.density = Some(density);
+		self
+	}
+
+	pub fn with_svg(mut self, prefix : &str) -> Self {
+		self.svg_prefix = Some(prefix.to_string());
+		self
+	}
+}
+
+// 1 for black, 0 for white, image resized to w x h
+pub fn load_density(name : &str, w : u32, h : u32) -> io::Result<Vec<f32>> {
+	let img = image::open(name).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", name, err)))?.into_luma8();
+	let img = if img.dimensions() == (w, h) { img } else { image::imageops::resize(&img, w, h, FilterType::Triangle) };
+	Ok(img.pixels().map(|p| 1. - p[0] as f32 / 255.).collect())
+}
+
+// black dots on white, for stippling, centers in image pixels
+pub fn write_dots_svg(name : &str, w : u32, h : u32, dots : &[(f64, f64)]) -> io::Result<()> {
+	let mut svg = String::new();
+	let _ = writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">", w, h, w, h);
+	let _ = writeln!(svg, "<rect width=\"{}\" height=\"{}\" fill=\"white\"/>", w, h);
+	for (x, y) in dots {
+		let _ = writeln!(svg, "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{}\"/>", x, y, DOT_RADIUS);
+	}
+	svg += "</svg>\n";
+	fs::write(name, svg)
+}
+
+impl Voronoi {
+	// weighted centroid of each cell, density per block of scale x scale pixels, None for cells without weight
+	pub fn centroids(&self, density : Option<&[f32]>, scale : u32) -> Vec<Option<(f64, f64)>> {
+		let (grid, n, dw) = (self.grid(), self.points.len(), self.w / scale);
+		let sums = (0..self.w * self.h).into_par_iter()
+			.fold(|| vec![(0_f64, 0_f64, 0_f64); n], |mut sums, index| {
+				let (i, j) = (index % self.w, index / self.w);
+				let wt = density.map_or(1., |d| d[((j / scale) * dw + i / scale) as usize] as f64);
+				if wt > 0. {
+					let s = &mut sums[self.nearest_grid(&grid, i, j, false, None).site];
+					*s = (s.0 + wt, s.1 + wt * i as f64, s.2 + wt * j as f64);
+				}
+				sums
+			})
+			.reduce(|| vec![(0., 0., 0.); n], |mut a, b| {
+				for (x, y) in a.iter_mut().zip(b.iter()) { *x = (x.0 + y.0, x.1 + y.1, x.2 + y.2) }
+				a
+			});
+		sums.iter().map(|&(wt, x, y)| if wt > 0. { Some((x / wt, y / wt)) } else { None }).collect()
+	}
+
+	// site centers in the pixels of a grid 'scale' times coarser
+	fn dots(&self, scale : u32) -> Vec<(f64, f64)> {
+		self.points.iter().map(|p| ((p.x as f64 + 0.5) / scale as f64, (p.y as f64 + 0.5) / scale as f64)).collect()
+	}
+
+	// relaxes the sites in place, returns the largest site move of each iteration
+	pub fn lloyd(&mut self, opts : &Lloyd) -> io::Result<Vec<f64>> {
+		if let Some(d) = &opts.density {
+			if d.len() != (self.w * self.h) as usize { return Err(io::Error::new(io::ErrorKind::InvalidInput, "density size differs from the image")) }
+		}
+		let s = opts.scale.max(1);
+		let points = self.points.iter().map(|p| Point::new(p.x * s + s / 2, p.y * s + s / 2, p.color)).collect();
+		let mut fine = Voronoi::from_points(self.w * s, self.h * s, points).with_metric(self.metric);
+
+		let svg = |fine : &Voronoi, k : u32| match &opts.svg_prefix {
+			Some(prefix) => write_dots_svg(&format!("{}_{:03}.svg", prefix, k), self.w, self.h, &fine.dots(s)),
+			None => Ok(()),
+		};
+		svg(&fine, 0)?;
+
+		let (mut moves, mut moved) = (vec![], vec![false; self.points.len()]);
+		for k in 1..=opts.max_iters {
+			let centroids = fine.centroids(opts.density.as_deref(), s);
+			let mut max_move = 0_f64;
+			for ((p, c), moved) in fine.points.iter_mut().zip(centroids.iter()).zip(moved.iter_mut()) {
+				if let Some((x, y)) = *c {
+					let (x, y) = (x.round() as u32, y.round() as u32);
+					max_move = max_move.max(Metric::Euclidean.distance(x as i64 - p.x as i64, y as i64 - p.y as i64) / s as f64);
+					*moved |= (x, y) != (p.x, p.y);
+					p.x = x;
+					p.y = y;
+				}
+			}
+			moves.push(max_move);
+			svg(&fine, k)?;
+			if max_move <= opts.tolerance { break }
+		}
+
+		for ((p, (x, y)), moved) in self.points.iter_mut().zip(fine.dots(s)).zip(moved) {
+			if moved {
+				p.x = (x - 0.5).round().min(self.w as f64 - 1.) as u32;
+				p.y = (y - 0.5).round().min(self.h as f64 - 1.) as u32;
+			}
+		}
+		Ok(moves)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn cell_sizes(v : &Voronoi) -> Vec<f64> {
+		let mut sizes = vec![0.; v.points.len()];
+		for n in v.nearest_all(false) { sizes[n.site] += 1. }
+		sizes
+	}
+
+	fn deviation(x : &[f64]) -> f64 {
+		let mean = x.iter().sum::<f64>() / x.len() as f64;
+		(x.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / x.len() as f64).sqrt()
+	}
+
+	#[test]
+	fn lloyd() {
+		let mut v = Voronoi::seeded(96, 64, 24, 2);
+		let before = deviation(&cell_sizes(&v));
+		let moves = v.lloyd(&Lloyd::new(100, 0.)).unwrap();
+		assert!(moves.len() < 100 && *moves.last().unwrap() == 0.); // converged, sites stopped
+		assert!(deviation(&cell_sizes(&v)) < before / 3., "cells should even out");
+
+		// density ramping up to the right, the cvt has sites ∝ √density: mean x 0.6 w
+		let (w, h) = (80, 40);
+		let density : Vec<f32> = (0..w * h).map(|index| (index % w) as f32 / w as f32).collect();
+		let mean_x = |v : &Voronoi| v.points().iter().map(|p| p.x as f64 + 0.5).sum::<f64>() / v.points().len() as f64;
+		let prefix = std::env::temp_dir().join("voronoi_lloyd").to_str().unwrap().to_string();
+		let mut shifts = vec![];
+		for &scale in [1, 4].iter() {
+			let mut v = Voronoi::seeded(w, h, 30, 7);
+			let before = mean_x(&v);
+			let moves = v.lloyd(&Lloyd::new(200, 0.).with_scale(scale).with_density(density.clone()).with_svg(&prefix)).unwrap();
+			shifts.push(mean_x(&v) - before);
+			for k in 0..=moves.len() {
+				let name = format!("{}_{:03}.svg", prefix, k);
+				assert_eq!(fs::read_to_string(&name).unwrap().matches("<circle").count(), 30);
+				let _ = fs::remove_file(name);
+			}
+		}
+		assert!(shifts[1] > shifts[0] && shifts[1] > 0.08 * w as f64, "{:?}", shifts);
+
+		// no weight, no move, sites off the image too
+		let mut v = Voronoi::from_points(40, 40, vec![Point::new(1, 8, 0), Point::new(35, 35, 0), Point::new(70, 10, 0)]);
+		let density : Vec<f32> = (0..40 * 40).map(|index| if index % 40 < 10 && index / 40 < 10 { 1. } else { 0. }).collect();
+		v.lloyd(&Lloyd::new(20, 0.).with_scale(3).with_density(density)).unwrap();
+		assert_eq!(v.points(), [Point::new(5, 5, 0), Point::new(35, 35, 0), Point::new(70, 10, 0)]);
+		assert!(v.lloyd(&Lloyd::new(5, 0.).with_density(vec![1.; 10])).is_err());
+	}
+}
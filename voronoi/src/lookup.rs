@@ -0,0 +1,242 @@
+/*
+	nearest site lookups, all three give the brute force result bit for bit
+		brute 		every pixel scans every site, O(w h n)
+		grid 		sites bucketed in square cells of ~1 site, rings of cells around the pixel are scanned until
+					the cells left are farther than the answer. the chebyshev distance to the rings is a lower
+					bound for every metric, ties go to the lowest site index as in the brute force scan
+		jump flood 	jfa (1+jfa: a step 1 pass first) propagates site indices with steps w/2, w/4.. 1 in parallel,
+					it's approximate, its answer only seeds the exact grid search
+*/
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lookup {
+	Brute,
+	Grid,
+	JumpFlood,
+}
+
+impl Lookup {
+	pub fn parse(name : &str) -> Result<Self, String> {
+		match name {
+			"brute" => Ok(Lookup::Brute),
+			"grid" 	=> Ok(Lookup::Grid),
+			"jfa" 	=> Ok(Lookup::JumpFlood),
+			_ => Err(format!("unknown lookup '{}': brute grid jfa", name)),
+		}
+	}
+}
+
+const NO_SITE : u32 = u32::MAX;
+
+// sites of cell k: index[start[k]..start[k + 1]], in index order
+#[derive(Debug)]
+pub struct Grid {
+	cell  : u32,
+	gw 	  : u32,
+	gh 	  : u32,
+	start : Vec<u32>,
+	index : Vec<u32>,
+}
+
+impl Grid {
+	pub fn new(points : &[Point], w : u32, h : u32) -> Self {
+		let cell = ((w as f64 * h as f64 / points.len().max(1) as f64).sqrt().round() as u32).max(1);
+		let (gw, gh) = (w.div_ceil(cell).max(1), h.div_ceil(cell).max(1));
+		let cell_of = |p : &Point| ((p.y / cell).min(gh - 1) * gw + (p.x / cell).min(gw - 1)) as usize; // sites off the image in border cells
+
+		let mut start = vec![0_u32; (gw * gh + 1) as usize];
+		for p in points { start[cell_of(p) + 1] += 1 }
+		for k in 1..start.len() { start[k] += start[k - 1] }
+		let (mut fill, mut index) = (start.clone(), vec![0; points.len()]);
+		for (it, p) in points.iter().enumerate() {
+			let k = cell_of(p);
+			index[fill[k] as usize] = it as u32;
+			fill[k] += 1;
+		}
+		Self { cell, gw, gh, start, index }
+	}
+
+	pub fn cell(&self) -> u32 { self.cell }
+
+	fn cell_sites(&self, cx : i64, cy : i64, visit : &mut impl FnMut(usize)) {
+		if cx < 0 || cy < 0 || cx >= self.gw as i64 || cy >= self.gh as i64 { return }
+		let k = (cy * self.gw as i64 + cx) as usize;
+		for &it in &self.index[self.start[k] as usize..self.start[k + 1] as usize] { visit(it as usize) }
+	}
+
+	// cells at chebyshev distance r from (cx, cy)
+	fn ring(&self, cx : i64, cy : i64, r : i64, visit : &mut impl FnMut(usize)) {
+		for y in cy - r..=cy + r {
+			if y == cy - r || y == cy + r {
+				for x in cx - r..=cx + r { self.cell_sites(x, y, visit) }
+			} else {
+				self.cell_sites(cx - r, y, visit);
+				self.cell_sites(cx + r, y, visit);
+			}
+		}
+	}
+
+	// visits the sites ring by ring until the rings left are farther than the limit returned by the last visit
+	pub fn search(&self, i : u32, j : u32, mut limit : f64, mut visit : impl FnMut(usize) -> f64) {
+		let (cx, cy) = ((i / self.cell).min(self.gw - 1) as i64, (j / self.cell).min(self.gh - 1) as i64);
+		let (x, y, c) = (i as i64, j as i64, self.cell as i64);
+		for r in 0.. {
+			self.ring(cx, cy, r, &mut |it| limit = visit(it));
+			if cx - r <= 0 && cy - r <= 0 && cx + r >= self.gw as i64 - 1 && cy + r >= self.gh as i64 - 1 { break }
+
+			// sites beyond ring r: x <= (cx - r) c - 1 or x >= (cx + r + 1) c, same for y
+			let outside = (x - (cx - r) * c + 1).min((cx + r + 1) * c - x).min(y - (cy - r) * c + 1).min((cy + r + 1) * c - y);
+			if outside as f64 > limit { break }
+		}
+	}
+}
+
+impl Voronoi {
+	pub fn with_lookup(mut self, lookup : Lookup) -> Self {
+		self.lookup = lookup;
+		self
+	}
+
+	pub fn grid(&self) -> Grid { Grid::new(&self.points, self.w, self.h) }
+
+	// same as nearest(), f2 only exact when 'second', from an optional first guess
+	pub fn nearest_grid(&self, grid : &Grid, i : u32, j : u32, second : bool, guess : Option<usize>) -> Nearest {
+		let mut n = Nearest { site : usize::MAX, f1 : f64::INFINITY, f2 : f64::INFINITY };
+		let offer = |n : &mut Nearest, it : usize| {
+			let d = self.distance(i, j, &self.points[it]);
+			if d < n.f1 || d == n.f1 && it < n.site {
+				*n = Nearest { site : it, f1 : d, f2 : n.f1 };
+			} else if d < n.f2 {
+				n.f2 = d;
+			}
+		};
+		if let Some(it) = guess { offer(&mut n, it) }
+
+		let limit = if second { n.f2 } else { n.f1 };
+		grid.search(i, j, limit, |it| {
+			if Some(it) != guess { offer(&mut n, it) }
+			if second { n.f2 } else { n.f1 }
+		});
+		if n.f2.is_infinite() { n.f2 = n.f1 }
+		n
+	}
+
+	// edge_distance() for euclidean, bisector distances are >= (|p-b| - |p-a|) / 2
+	pub fn edge_distance_grid(&self, grid : &Grid, i : u32, j : u32, n : &Nearest) -> f64 {
+		let a = &self.points[n.site];
+		let mut edge = f64::INFINITY;
+		grid.search(i, j, f64::INFINITY, |it| {
+			let b = &self.points[it];
+			if it != n.site && (b.x, b.y) != (a.x, a.y) { edge = edge.min(self.bisector_distance(i, j, a, b)) }
+			n.f1 + 2. * edge
+		});
+		edge
+	}
+
+	// site index per pixel by jump flooding, approximate, NO_SITE where no site reached
+	pub fn jump_flood(&self) -> Vec<u32> {
+		let (w, h) = (self.w, self.h);
+		let mut sites = vec![NO_SITE; (w * h) as usize];
+		for (it, p) in self.points.iter().enumerate().rev() { // lowest index wins a shared pixel
+			if p.x < w && p.y < h { sites[(p.y * w + p.x) as usize] = it as u32 }
+		}
+
+		let mut steps = vec![1];
+		let mut k = w.max(h).next_power_of_two() / 2;
+		while k >= 1 { steps.push(k); k /= 2 }
+
+		for &k in steps.iter() {
+			let k = k as i64;
+			sites = (0..w * h).into_par_iter().map(|index| {
+				let (i, j) = (index % w, index / w);
+				let mut best = (f64::INFINITY, NO_SITE);
+				for dy in [-k, 0, k].iter() {
+					for dx in [-k, 0, k].iter() {
+						let (x, y) = (i as i64 + dx, j as i64 + dy);
+						if x < 0 || y < 0 || x >= w as i64 || y >= h as i64 { continue }
+						let it = sites[(y as u32 * w + x as u32) as usize];
+						if it == NO_SITE { continue }
+						let d = (self.distance(i, j, &self.points[it as usize]), it);
+						if d < best { best = d }
+					}
+				}
+				best.1
+			}).collect();
+		}
+		sites
+	}
+
+	// nearest site per pixel with the selected lookup, f2 exact when 'second'
+	pub fn nearest_all(&self, second : bool) -> Vec<Nearest> {
+		let size = self.w * self.h;
+		match self.lookup {
+			Lookup::Brute => (0..size).into_par_iter().map(|index| self.nearest(index % self.w, index / self.w)).collect(),
+			Lookup::Grid => {
+				let grid = self.grid();
+				(0..size).into_par_iter().map(|index| self.nearest_grid(&grid, index % self.w, index / self.w, second, None)).collect()
+			}
+			Lookup::JumpFlood => {
+				let (grid, guess) = (self.grid(), self.jump_flood());
+				(0..size).into_par_iter().map(|index| {
+					let it = guess[index as usize];
+					self.nearest_grid(&grid, index % self.w, index / self.w, second, if it == NO_SITE { None } else { Some(it as usize) })
+				}).collect()
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::time::Instant;
+
+	#[test]
+	fn lookups() { // clustered, duplicate & off image sites, every metric
+		let mut points : Vec<Point> = (0..200).map(|k| Point::new(k * 7 % 31 + 40, k * 13 % 17 + 20, site_color(k as usize))).collect();
+		points.extend(vec![Point::new(5, 5, 1 << 8), Point::new(5, 5, 2 << 8), Point::new(500, 3, 3 << 8), Point::new(90, 70, 4 << 8)]);
+		for &metric in [Metric::Euclidean, Metric::Manhattan, Metric::Chebyshev, Metric::Minkowski(3.)].iter() {
+			let v = Voronoi::from_points(100, 80, points.clone()).with_metric(metric).with_lookup(Lookup::Brute);
+			let brute = v.nearest_all(true);
+			for &lookup in [Lookup::Grid, Lookup::JumpFlood].iter() {
+				assert_eq!(Voronoi { lookup, ..Voronoi::from_points(100, 80, points.clone()).with_metric(metric) }.nearest_all(true), brute, "{:?} {:?}", metric, lookup);
+			}
+		}
+
+		for &output in [Output::Cells, Output::F2MinusF1, Output::Edge].iter() {
+			let mut images = [Lookup::Brute, Lookup::Grid, Lookup::JumpFlood].iter().map(|&lookup| {
+				let mut v = Voronoi::seeded(120, 90, 300, 5).with_output(output).with_lookup(lookup);
+				v.generate();
+				v.image
+			});
+			let brute = images.next().unwrap();
+			assert!(images.all(|image| image == brute), "{:?}", output);
+		}
+	}
+
+	#[test]
+	#[ignore] // cargo test --release bench_lookup -- --ignored --nocapture
+	fn bench_lookup() {
+		let (w, h) = (400, 300);
+		for &n in [10, 100, 1000, 10_000, 100_000].iter() {
+			let brute_force = n <= 1000;
+			let mut times = vec![];
+			let mut images = vec![];
+			for &lookup in [Lookup::Brute, Lookup::Grid, Lookup::JumpFlood].iter() {
+				if lookup == Lookup::Brute && !brute_force { continue }
+				let mut v = Voronoi::seeded(w, h, n, 1).with_lookup(lookup);
+				let t = Instant::now();
+				v.generate();
+				times.push(format!("{:?} {:?}", lookup, Instant::now() - t));
+				images.push(v.image);
+			}
+			let v = Voronoi::seeded(w, h, n, 1).with_lookup(Lookup::Grid);
+			let exact = v.nearest_all(false);
+			let jfa_errors = v.jump_flood().iter().zip(exact.iter()).filter(|(&it, n)| it as usize != n.site).count();
+			println!("{:6} sites: {}, raw jfa {} pixels off", n, times.join(", "), jfa_errors);
+			assert!(images.iter().all(|image| *image == images[0]));
+		}
+	}
+}
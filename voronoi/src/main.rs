@@ -4,8 +4,107 @@ use std::time::Instant;
 
 fn main() {
     let mf = 6;
-    let (w, h, n_points) = (800*mf, 800*mf, 400*mf);
-    let mut v = Voronoi::new(w, h, n_points);
+    let (mut w, mut h, mut n_points) = (800*mf, 800*mf, 400*mf);
+    let (mut metric, mut output, mut colored, mut lookup) = (Metric::Euclidean, Output::Cells, false, Lookup::Grid);
+    let (mut seed, mut sites, mut export) = (None, None, None);
+    let (mut svg, mut delaunay) = (None, false);
+    let (mut lloyd, mut lloyd_scale, mut density, mut lloyd_svg) = (None, 1, None, None);
+
+    // voronoi [--size WxH] [--points n] [--seed n] [--sites in.csv|json] [--export-sites out.csv|json]
+    //         [--lloyd iters [tolerance]] [--lloyd-scale s] [--density image] [--lloyd-svg prefix]
+    //         [--svg out.svg] [--delaunay] [--lookup brute|grid|jfa] [--metric euclidean|manhattan|chebyshev|minkowski p] [--worley cells|f1|f2|f2-f1|edge] [--colored]
+    let mut args = std::env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--size"    => match args.next().as_deref().and_then(|s| s.split_once('x')).map(|(w, h)| (w.parse::<u32>(), h.parse::<u32>())) {
+                Some((Ok(sw), Ok(sh))) if sw > 0 && sh > 0 => { w = sw; h = sh }
+                _                                          => { eprintln!("bad size, expected WxH"); std::process::exit(2) }
+            },
+            "--points"  => match args.next().map(|n| n.parse::<u32>()) {
+                Some(Ok(n)) if n > 0 => n_points = n,
+                _                    => { eprintln!("bad point count"); std::process::exit(2) }
+            },
+            "--seed"    => match args.next().map(|n| n.parse::<u64>()) {
+                Some(Ok(n)) => seed = Some(n),
+                _           => { eprintln!("bad seed"); std::process::exit(2) }
+            },
+            "--lloyd"   => {
+                let iters = args.next().and_then(|n| n.parse::<u32>().ok());
+                let tolerance = args.next_if(|t| t.parse::<f64>().is_ok()).map_or(0., |t| t.parse::<f64>().unwrap());
+                match iters {
+                    Some(iters) => lloyd = Some((iters, tolerance)),
+                    None        => { eprintln!("bad lloyd iteration count"); std::process::exit(2) }
+                }
+            },
+            "--lloyd-scale" => match args.next().map(|s| s.parse::<u32>()) {
+                Some(Ok(s)) if s > 0 => lloyd_scale = s,
+                _                    => { eprintln!("bad lloyd scale"); std::process::exit(2) }
+            },
+            "--sites" | "--export-sites" | "--svg" | "--density" | "--lloyd-svg" => match args.next() {
+                Some(name) => match arg.as_str() {
+                    "--sites"     => sites = Some(name),
+                    "--svg"       => svg = Some(name),
+                    "--density"   => density = Some(name),
+                    "--lloyd-svg" => lloyd_svg = Some(name),
+                    _         => export = Some(name),
+                },
+                None       => { eprintln!("missing {} file", arg); std::process::exit(2) }
+            },
+            "--metric"  => {
+                let name = args.next().unwrap_or_default();
+                let p = if name == "minkowski" { args.next().and_then(|p| p.parse::<f64>().ok()) } else { None };
+                match Metric::parse(&name, p) {
+                    Ok(m)    => metric = m,
+                    Err(err) => { eprintln!("{}", err); std::process::exit(2) }
+                }
+            },
+            "--worley"  => match Output::parse(&args.next().unwrap_or_default()) {
+                Ok(o)    => output = o,
+                Err(err) => { eprintln!("{}", err); std::process::exit(2) }
+            },
+            "--colored" => colored = true,
+            "--delaunay" => delaunay = true,
+            "--lookup"  => match Lookup::parse(&args.next().unwrap_or_default()) {
+                Ok(l)    => lookup = l,
+                Err(err) => { eprintln!("{}", err); std::process::exit(2) }
+            },
+            _ => { eprintln!("unknown option '{}'", arg); std::process::exit(2) }
+        }
+    }
+
+    if svg.is_some() && metric != Metric::Euclidean {
+        eprintln!("--svg draws the exact euclidean diagram, not the {:?} metric", metric);
+        std::process::exit(2)
+    }
+
+    let v = match (&sites, seed) {
+        (Some(name), _) => match Voronoi::from_file(w, h, name) {
+            Ok(v)    => v,
+            Err(err) => { eprintln!("{}", err); std::process::exit(1) }
+        },
+        (None, Some(seed)) => Voronoi::seeded(w, h, n_points, seed),
+        (None, None)       => Voronoi::new(w, h, n_points),
+    };
+    let mut v = v.with_metric(metric).with_output(output).with_colored(colored).with_lookup(lookup);
+    if let Some((iters, tolerance)) = lloyd {
+        let mut opts = Lloyd::new(iters, tolerance).with_scale(lloyd_scale);
+        if let Some(name) = &density {
+            match load_density(name, w, h) {
+                Ok(d)    => opts = opts.with_density(d),
+                Err(err) => { eprintln!("{}", err); std::process::exit(1) }
+            }
+        }
+        if let Some(prefix) = &lloyd_svg { opts = opts.with_svg(prefix) }
+        let t = Instant::now();
+        match v.lloyd(&opts) {
+            Ok(moves) => println!("lloyd: {} iterations, last move {:.2} pix, lap: {:?}", moves.len(), moves.last().cloned().unwrap_or(0.), Instant::now()-t),
+            Err(err)  => { eprintln!("lloyd: {}", err); std::process::exit(1) }
+        }
+    }
+    if let Some(name) = &export {
+        if let Err(err) = v.write_sites(name, true) { eprintln!("{}: {}", name, err); std::process::exit(1) }
+    }
+    let n_points = v.points().len();
 
     println!("generating voronoi for {}x{}={} pix, {} points...", w, h, w*h, n_points);
     let t = Instant::now();
@@ -15,4 +114,14 @@ fn main() {
     println!("lap: {:?}", Instant::now()-t);
 
     v.write_png("voronoi.png");
+
+    if let Some(name) = &svg {
+        let t = Instant::now();
+        let d : Diagram = match v.diagram() {
+            Ok(d)    => d,
+            Err(err) => { eprintln!("{}: {}", name, err); std::process::exit(1) }
+        };
+        if let Err(err) = v.write_svg(name, &d, delaunay) { eprintln!("{}: {}", name, err); std::process::exit(1) }
+        println!("{} cells, {} delaunay triangles, lap: {:?}", d.cells.iter().filter(|c| !c.is_empty()).count(), d.triangles.len(), Instant::now()-t);
+    }
 }
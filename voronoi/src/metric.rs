@@ -0,0 +1,42 @@
+/*
+	distance between a pixel and a site from signed offsets
+		euclidean 		sqrt(dx² + dy²)
+		manhattan 		|dx| + |dy|
+		chebyshev 		max(|dx|, |dy|)
+		minkowski p 	(|dx|^p + |dy|^p)^(1/p), p >= 1, 1: manhattan, 2: euclidean, oo: chebyshev
+*/
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Metric {
+	#[default]
+	Euclidean,
+	Manhattan,
+	Chebyshev,
+	Minkowski(f64),
+}
+
+impl Metric {
+	pub fn distance(&self, dx : i64, dy : i64) -> f64 {
+		let (x, y) = (dx.abs(), dy.abs());
+		match *self {
+			Metric::Euclidean 	 => ((x * x + y * y) as f64).sqrt(), // exact square, same order as the integer distances
+			Metric::Manhattan 	 => (x + y) as f64,
+			Metric::Chebyshev 	 => x.max(y) as f64,
+			Metric::Minkowski(p) => { // scaled by the larger offset, x^p overflows for large p
+				let m = x.max(y) as f64;
+				if m == 0. { 0. } else { m * ((x as f64 / m).powf(p) + (y as f64 / m).powf(p)).powf(1. / p) }
+			}
+		}
+	}
+
+	pub fn parse(name : &str, p : Option<f64>) -> Result<Self, String> {
+		match (name, p) {
+			("euclidean", _) => Ok(Metric::Euclidean),
+			("manhattan", _) => Ok(Metric::Manhattan),
+			("chebyshev", _) => Ok(Metric::Chebyshev),
+			("minkowski", Some(p)) if p >= 1. => Ok(Metric::Minkowski(p)),
+			("minkowski", _) => Err("minkowski needs p >= 1".to_string()),
+			_ => Err(format!("unknown metric '{}': euclidean manhattan chebyshev minkowski", name)),
+		}
+	}
+}
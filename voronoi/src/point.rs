@@ -2,7 +2,7 @@
 	x,y point
 */
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Point {
 	pub x		:u32,
 	pub y		:u32,
@@ -0,0 +1,189 @@
+/*
+	site lists import / export, colors optional
+		csv 	x,y[,#rrggbb] per line, blank lines, '#' comments & an 'x,y..' header are skipped
+		json 	[{"x": 10, "y": 20, "color": "#ff8000"}, ..], other keys & their values ignored
+	sites without a color get site_color(index), the same in every run
+*/
+
+use super::point::*;
+use std::fs;
+use std::io;
+
+fn invalid(msg : String) -> io::Error { io::Error::new(io::ErrorKind::InvalidData, msg) }
+
+// splitmix64 of the site index
+pub fn site_color(k : usize) -> u32 {
+	let mut z = (k as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+	((z ^ (z >> 31)) >> 32) as u32 & 0xffff_ff00
+}
+
+pub fn parse_color(s : &str) -> Option<u32> {
+	let hex = s.trim().strip_prefix('#')?;
+	if hex.len() != 6 { return None }
+	u32::from_str_radix(hex, 16).ok().map(|rgb| rgb << 8)
+}
+
+pub fn format_color(color : u32) -> String { format!("#{:06x}", color >> 8) }
+
+pub fn parse_csv(name : &str, text : &str) -> io::Result<Vec<Point>> {
+	let mut points = vec![];
+	for (ln, line) in text.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') || line.starts_with('x') { continue }
+
+		let err = |msg : &str| invalid(format!("{}:{}: {}", name, ln + 1, msg));
+		let fields : Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+		if fields.len() < 2 || fields.len() > 3 { return Err(err("expected x,y[,#rrggbb]")) }
+		let (x, y) = match (fields[0].parse::<u32>(), fields[1].parse::<u32>()) {
+			(Ok(x), Ok(y)) => (x, y),
+			_ => return Err(err("bad coordinates")),
+		};
+		let color = match fields.get(2) {
+			Some(c) => parse_color(c).ok_or_else(|| err("bad color, expected #rrggbb"))?,
+			None => site_color(points.len()),
+		};
+		points.push(Point::new(x, y, color));
+	}
+	Ok(points)
+}
+
+pub fn sites_to_csv(points : &[Point], colors : bool) -> String {
+	let mut s = String::from(if colors { "x,y,color\n" } else { "x,y\n" });
+	for p in points {
+		s += &if colors { format!("{},{},{}\n", p.x, p.y, format_color(p.color)) } else { format!("{},{}\n", p.x, p.y) };
+	}
+	s
+}
+
+// just enough json for a site list: an array of objects, x & y numbers, color a string, other values skipped
+struct Json<'a> {
+	name : &'a str,
+	text : &'a [u8],
+	pos  : usize,
+}
+
+enum Value {
+	Number(f64),
+	Text(String),
+	Other, // null, true, false, array, object
+}
+
+impl<'a> Json<'a> {
+	fn err(&self, msg : &str) -> io::Error {
+		let line = self.text[..self.pos.min(self.text.len())].iter().filter(|&&c| c == b'\n').count() + 1;
+		invalid(format!("{}:{}: {}", self.name, line, msg))
+	}
+
+	fn peek(&mut self) -> Option<u8> {
+		while self.pos < self.text.len() && self.text[self.pos].is_ascii_whitespace() { self.pos += 1 }
+		self.text.get(self.pos).cloned()
+	}
+
+	fn expect(&mut self, c : u8) -> io::Result<()> {
+		if self.peek() != Some(c) { return Err(self.err(&format!("expected '{}'", c as char))) }
+		self.pos += 1;
+		Ok(())
+	}
+
+	fn string(&mut self) -> io::Result<String> { // escapes are kept as is
+		self.expect(b'"')?;
+		let start = self.pos;
+		while self.pos < self.text.len() && self.text[self.pos] != b'"' {
+			self.pos += if self.text[self.pos] == b'\\' { 2 } else { 1 };
+		}
+		self.pos = self.pos.min(self.text.len());
+		let s = String::from_utf8_lossy(&self.text[start..self.pos]).into_owned();
+		self.expect(b'"')?;
+		Ok(s)
+	}
+
+	// array or object, contents skipped
+	fn nested(&mut self, close : u8) -> io::Result<()> {
+		self.pos += 1;
+		while self.peek() != Some(close) {
+			if close == b'}' {
+				self.string()?;
+				self.expect(b':')?;
+			}
+			self.value()?;
+			if self.peek() == Some(b',') { self.pos += 1 } else { break }
+		}
+		self.expect(close)
+	}
+
+	fn value(&mut self) -> io::Result<Value> {
+		match self.peek() {
+			Some(b'"') => return Ok(Value::Text(self.string()?)),
+			Some(b'[') => return self.nested(b']').map(|_| Value::Other),
+			Some(b'{') => return self.nested(b'}').map(|_| Value::Other),
+			_ => {}
+		}
+		for word in ["null", "true", "false"].iter() {
+			if self.text[self.pos..].starts_with(word.as_bytes()) {
+				self.pos += word.len();
+				return Ok(Value::Other)
+			}
+		}
+		let start = self.pos;
+		while self.pos < self.text.len() && (self.text[self.pos].is_ascii_digit() || b"+-.eE".contains(&self.text[self.pos])) { self.pos += 1 }
+		std::str::from_utf8(&self.text[start..self.pos]).ok().and_then(|s| s.parse().ok()).map(Value::Number).ok_or_else(|| self.err("expected a json value"))
+	}
+
+	fn site(&mut self, index : usize) -> io::Result<Point> {
+		let (mut x, mut y, mut color) = (None, None, None);
+		self.expect(b'{')?;
+		while self.peek() != Some(b'}') {
+			let key = self.string()?;
+			self.expect(b':')?;
+			match (key.as_str(), self.value()?) {
+				("x", Value::Number(v)) if v >= 0. && v <= u32::MAX as f64 && v.fract() == 0. => x = Some(v as u32),
+				("y", Value::Number(v)) if v >= 0. && v <= u32::MAX as f64 && v.fract() == 0. => y = Some(v as u32),
+				("color", Value::Text(c)) => color = Some(parse_color(&c).ok_or_else(|| self.err("bad color, expected #rrggbb"))?),
+				("x", _) | ("y", _) | ("color", _) => return Err(self.err(&format!("bad {}", key))),
+				_ => {}
+			}
+			if self.peek() == Some(b',') { self.pos += 1 } else { break }
+		}
+		self.expect(b'}')?;
+		match (x, y) {
+			(Some(x), Some(y)) => Ok(Point::new(x, y, color.unwrap_or_else(|| site_color(index)))),
+			_ => Err(self.err("site without x & y")),
+		}
+	}
+
+	fn sites(&mut self) -> io::Result<Vec<Point>> {
+		let mut points = vec![];
+		self.expect(b'[')?;
+		while self.peek() != Some(b']') {
+			points.push(self.site(points.len())?);
+			if self.peek() == Some(b',') { self.pos += 1 } else { break }
+		}
+		self.expect(b']')?;
+		if self.peek().is_some() { return Err(self.err("trailing data")) }
+		Ok(points)
+	}
+}
+
+pub fn parse_json(name : &str, text : &str) -> io::Result<Vec<Point>> { Json { name, text : text.as_bytes(), pos : 0 }.sites() }
+
+pub fn sites_to_json(points : &[Point], colors : bool) -> String {
+	let sites : Vec<String> = points.iter().map(|p|
+		if colors { format!("  {{\"x\": {}, \"y\": {}, \"color\": \"{}\"}}", p.x, p.y, format_color(p.color)) }
+		else { format!("  {{\"x\": {}, \"y\": {}}}", p.x, p.y) }
+	).collect();
+	format!("[\n{}\n]\n", sites.join(",\n"))
+}
+
+fn is_json(name : &str) -> bool { name.to_lowercase().ends_with(".json") }
+
+// .json or csv by extension
+pub fn read_sites(name : &str) -> io::Result<Vec<Point>> {
+	let text = fs::read_to_string(name)?;
+	if is_json(name) { parse_json(name, &text) } else { parse_csv(name, &text) }
+}
+
+pub fn write_sites(name : &str, points : &[Point], colors : bool) -> io::Result<()> {
+	fs::write(name, if is_json(name) { sites_to_json(points, colors) } else { sites_to_csv(points, colors) })
+}
@@ -1,56 +1,92 @@
 #[path = "point.rs"]mod point;
-use point::*;
+#[path = "metric.rs"]mod metric;
+#[path = "worley.rs"]mod worley;
+#[path = "sites.rs"]mod sites;
+#[path = "lookup.rs"]mod lookup;
+#[path = "delaunay.rs"]mod delaunay;
+#[path = "lloyd.rs"]mod lloyd;
+pub use point::*;
+pub use metric::*;
+pub use worley::*;
+pub use sites::*;
+pub use lookup::*;
+pub use delaunay::*;
+pub use lloyd::*;
 
 use rayon::prelude::*;
 use image::{ImageBuffer, Rgb};
 use std::convert::TryInto;
+use std::io;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const CENTER_RADIUS : f64 = 2.; // sites drawn as black dots in cell renders
 
 #[derive(Debug)]
 pub struct Voronoi {
 	w : u32,
 	h : u32,
-	n_points : u32,
 	points  : Vec<Point>,
 	image	: Vec<u32>,
+	metric 	: Metric,
+	output 	: Output,
+	colored : bool, // worley outputs in the cell colors, else gray
+	lookup 	: Lookup, // nearest site search, same image with any
 }
 
 impl Voronoi {
-	pub fn new(w : u32, h: u32, n_points : u32) -> Self {
-		fn rnd_u32(d : u32) -> u32 { rand::random::<u32>() % d }
+	pub fn new(w : u32, h: u32, n_points : u32) -> Self { Self::seeded(w, h, n_points, rand::random()) }
 
-		let points = (0..n_points).into_par_iter().map(
-			|_| Point::new(rnd_u32(w), rnd_u32(h), rnd_u32(0xffff_ffff) ) 
+	// same seed, same sites & colors on every platform
+	pub fn seeded(w : u32, h: u32, n_points : u32, seed : u64) -> Self {
+		let mut rng = ChaCha8Rng::seed_from_u64(seed);
+		let points = (0..n_points).map(
+			|_| Point::new(rng.gen_range(0..w), rng.gen_range(0..h), rng.gen::<u32>())
 		).collect();
 
-		
-		Voronoi { w, h, n_points, points, image: vec![] }
+		Self::from_points(w, h, points)
+	}
+
+	pub fn from_points(w : u32, h: u32, points : Vec<Point>) -> Self {
+		Voronoi { w, h, points, image: vec![], metric : Metric::default(), output : Output::Cells, colored : false, lookup : Lookup::Grid }
+	}
+
+	// csv or json site list
+	pub fn from_file(w : u32, h: u32, name : &str) -> io::Result<Self> {
+		let points = read_sites(name)?;
+		if points.is_empty() { return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}: no sites", name))) }
+		Ok(Self::from_points(w, h, points))
+	}
+
+	pub fn points(&self) -> &[Point] { &self.points }
+
+	pub fn write_sites(&self, name : &str, colors : bool) -> io::Result<()> { write_sites(name, &self.points, colors) }
+
+	pub fn with_metric(mut self, metric : Metric) -> Self {
+		self.metric = metric;
+		self
+	}
+
+	pub fn with_output(mut self, output : Output) -> Self {
+		self.output = output;
+		self
+	}
+
+	pub fn with_colored(mut self, colored : bool) -> Self {
+		self.colored = colored;
+		self
+	}
+
+	// signed offsets, pixels left of or above the site are negative
+	pub fn distance(&self, x : u32, y : u32, p : &Point) -> f64 {
+		self.metric.distance(x as i64 - p.x as i64, y as i64 - p.y as i64)
 	}
 
 	pub fn generate(&mut self)  {
-		fn distance_sqr(x:u32, y:u32, p:&Point) -> u32 {
-			fn sqr(x:u32) -> u32 { x * x }
-			sqr(x - p.x) + sqr(y - p.y)
-		}
-		self.image = (0 .. self.w * self.h).into_par_iter().map(
-			|index| {
-				let (i, j) = (index % self.w, index / self.w);
-				let mut ind		 :usize = 0;
-				let mut is_center:bool  = false;
-
-				let mut dist = distance_sqr(i, j, &self.points[0]);
-
-				for it in 1..self.points.len() {
-					let d = distance_sqr(i, j, &self.points[it]);
-
-					if d < 4 { is_center=true; break }
-					
-					if d < dist { 
-						dist = d;
-						ind = it;
-					}
-				}
-				if is_center {0} else { self.points[ind].color }
-			}
+		if self.output != Output::Cells { self.image = self.worley_image(); return }
+
+		self.image = self.nearest_all(false).par_iter().map(
+			|n| if n.f1 < CENTER_RADIUS {0} else { self.points[n.site].color }
 		).collect()
 	}
 
@@ -66,4 +102,89 @@ impl Voronoi {
 		imgbuf.save(name).unwrap();
 	}
 	
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn sites(w : u32, h : u32, points : Vec<Point>) -> Voronoi { Voronoi::from_points(w, h, points) }
+
+	fn fnv1a(image : &[u32]) -> u64 { // stable across rust versions, unlike DefaultHasher
+		image.iter().flat_map(|c| c.to_le_bytes()).fold(0xcbf2_9ce4_8422_2325, |h, b| (h ^ b as u64).wrapping_mul(0x100_0000_01b3))
+	}
+
+	#[test]
+	fn seeded() {
+		let (mut a, b) = (Voronoi::seeded(64, 48, 20, 42), Voronoi::seeded(64, 48, 20, 42));
+		assert_eq!(a.points(), b.points());
+		assert_ne!(a.points(), Voronoi::seeded(64, 48, 20, 43).points());
+		assert_eq!(a.points[0], Point::new(14, 32, 628_724_104));
+		a.generate();
+		assert_eq!(fnv1a(&a.image), 0xf132_eb1e_5109_8f0d);
+	}
+
+	#[test]
+	fn site_files() {
+		let points = vec![Point::new(1, 2, 0xff80_0000), Point::new(30, 4, 0x0012_ab00), Point::new(7, 9, 0x0000_0100)];
+		for &colors in [true, false].iter() {
+			let (csv, json) = (sites_to_csv(&points, colors), sites_to_json(&points, colors));
+			for read in [parse_csv("s.csv", &csv).unwrap(), parse_json("s.json", &json).unwrap()].iter() {
+				let xy = |p : &[Point]| p.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+				assert_eq!(xy(read), xy(&points));
+				if colors { assert_eq!(read, &points) } else { assert_eq!(read[1].color, site_color(1)) }
+			}
+		}
+		assert_eq!(parse_csv("s.csv", "# sites\nx,y\n\n 3 , 4 , #00ff00\n").unwrap(), vec![Point::new(3, 4, 0x00ff_0000)]);
+		assert_eq!(parse_json("s.json", r##"[{"y": 4, "id": "a", "x": 3, "color": "#00ff00"}]"##).unwrap(), vec![Point::new(3, 4, 0x00ff_0000)]);
+		let err = parse_csv("s.csv", "1,2\n3,x\n").unwrap_err().to_string();
+		assert_eq!(err, "s.csv:2: bad coordinates");
+		assert!(parse_json("s.json", "[{\"x\": 1}]").is_err());
+		assert!(parse_json("s.json", "[{\"x\": -1, \"y\": 2}]").is_err());
+		assert!(parse_json("s.json", "[{\"x\": 1e20, \"y\": 2}]").is_err());
+		let extra = r#"[{"x": 3, "tag": null, "ok": true, "no": false, "w": [1, [2, "a\"b"], {"k": {}}], "meta": {"a": [], "b": "c"}, "y": 4}]"#;
+		assert_eq!(parse_json("s.json", extra).unwrap(), vec![Point::new(3, 4, site_color(0))]);
+		assert!(parse_json("s.json", "[{\"x\": 1, \"y\": 2, \"t\": nil}]").is_err());
+		assert!(parse_csv("s.csv", "1,2,red\n").is_err());
+
+		let name = std::env::temp_dir().join("voronoi_sites_test.json");
+		let v = sites(40, 20, points.clone());
+		v.write_sites(name.to_str().unwrap(), true).unwrap();
+		assert_eq!(Voronoi::from_file(40, 20, name.to_str().unwrap()).unwrap().points(), &points[..]);
+		let _ = std::fs::remove_file(name);
+	}
+
+	#[test]
+	fn metrics() {
+		let v = sites(20, 10, vec![Point::new(15, 8, 0xff00_0000), Point::new(3, 2, 0x00ff_0000)]);
+		assert_eq!(v.distance(10, 5, &v.points[0]), 34_f64.sqrt()); // left of & above the site
+		assert_eq!(v.with_metric(Metric::Manhattan).distance(10, 5, &Point::new(15, 8, 0)), 8.);
+		assert_eq!(Metric::Chebyshev.distance(-5, 3), 5.);
+		assert!((Metric::Minkowski(2.).distance(3, -4) - 5.).abs() < 1e-12);
+		assert!((Metric::Minkowski(1.).distance(3, -4) - 7.).abs() < 1e-12);
+		assert!((Metric::Minkowski(150.).distance(300, 0) - 300.).abs() < 1e-9 && Metric::Minkowski(3.).distance(0, 0) == 0.);
+		assert!((Metric::Minkowski(500.).distance(-1000, 999) - 1000.).abs() < 2.); // near chebyshev, no overflow
+
+		let mut v = sites(20, 10, vec![Point::new(15, 8, 0xff00_0000), Point::new(3, 2, 0x00ff_0000)]);
+		v.generate();
+		assert_eq!(v.image[0], 0x00ff_0000); // top left, nearest to the second site
+		assert_eq!(v.image[(5 * 20 + 19) as usize], 0xff00_0000);
+		assert_eq!(v.image[(8 * 20 + 15) as usize], 0); // site
+	}
+
+	#[test]
+	fn worley() {
+		let points = vec![Point::new(2, 5, 0xff00_0000), Point::new(12, 5, 0x00ff_0000), Point::new(7, 15, 0x0000_ff00)];
+		let v = sites(16, 16, points).with_output(Output::Edge);
+		let n = v.nearest(4, 5);
+		assert_eq!((n.site, n.f1, n.f2), (0, 2., 8.));
+		let edge = v.worley();
+		assert_eq!(edge[(5 * 16 + 4) as usize], (3., 0)); // bisector x = 7
+		assert!(edge[(5 * 16 + 7) as usize].0.abs() < 1e-12);
+
+		let f2f1 = Voronoi { output : Output::F2MinusF1, ..v };
+		assert_eq!(f2f1.worley()[(5 * 16 + 4) as usize].0, 6.);
+		let image = f2f1.with_colored(true).worley_image();
+		assert!(image.iter().all(|&c| c & 0x00ff_ffff == 0 || c & 0xff00_ffff == 0 || c & 0xffff_00ff == 0)); // cell hues only
+	}
+}
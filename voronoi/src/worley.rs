@@ -0,0 +1,109 @@
+/*
+	worley / cellular noise, per pixel distances to the nearest sites
+		f1 		nearest site
+		f2 		second nearest site
+		f2-f1 	ridges along the cell borders
+		edge 	distance to the cell border, exact for euclidean, (f2 - f1)/2 for the other metrics
+	gray levels normalized to the image maximum, or the cell color scaled by it
+*/
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Output {
+	Cells, // flat cell colors, sites in black
+	F1,
+	F2,
+	F2MinusF1,
+	Edge,
+}
+
+impl Output {
+	pub fn parse(name : &str) -> Result<Self, String> {
+		match name {
+			"cells" => Ok(Output::Cells),
+			"f1" 	=> Ok(Output::F1),
+			"f2" 	=> Ok(Output::F2),
+			"f2-f1" => Ok(Output::F2MinusF1),
+			"edge" 	=> Ok(Output::Edge),
+			_ => Err(format!("unknown output '{}': cells f1 f2 f2-f1 edge", name)),
+		}
+	}
+}
+
+// two nearest sites of a pixel, f2 = f1 with a single site
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Nearest {
+	pub site : usize,
+	pub f1 	 : f64,
+	pub f2 	 : f64,
+}
+
+pub fn gray(v : u8) -> u32 { u32::from_be_bytes([v, v, v, 0]) }
+
+pub fn scale_color(color : u32, t : f64) -> u32 {
+	let c = color.to_be_bytes();
+	let s = |v : u8| (v as f64 * t).round().min(255.) as u8;
+	u32::from_be_bytes([s(c[0]), s(c[1]), s(c[2]), 0])
+}
+
+impl Voronoi {
+	pub fn nearest(&self, i : u32, j : u32) -> Nearest {
+		let mut n = Nearest { site : 0, f1 : f64::INFINITY, f2 : f64::INFINITY };
+		for (it, p) in self.points.iter().enumerate() {
+			let d = self.distance(i, j, p);
+			if d < n.f1 {
+				n = Nearest { site : it, f1 : d, f2 : n.f1 };
+			} else if d < n.f2 {
+				n.f2 = d;
+			}
+		}
+		if n.f2.is_infinite() { n.f2 = n.f1 }
+		n
+	}
+
+	// euclidean distance of pixel (i, j) to the bisector of sites a & b: (|p-b|² - |p-a|²) / 2|b-a|
+	pub fn bisector_distance(&self, i : u32, j : u32, a : &Point, b : &Point) -> f64 {
+		let sqr = |p : &Point| { let (dx, dy) = (i as i64 - p.x as i64, j as i64 - p.y as i64); (dx * dx + dy * dy) as f64 };
+		(sqr(b) - sqr(a)) / (2. * Metric::Euclidean.distance(b.x as i64 - a.x as i64, b.y as i64 - a.y as i64))
+	}
+
+	fn edge_distance(&self, i : u32, j : u32, n : &Nearest) -> f64 {
+		let a = &self.points[n.site];
+		self.points.iter().enumerate()
+			.filter(|(it, b)| *it != n.site && (b.x, b.y) != (a.x, a.y))
+			.map(|(_, b)| self.bisector_distance(i, j, a, b))
+			.fold(f64::INFINITY, f64::min)
+	}
+
+	// noise value per pixel & the cell it's in
+	pub fn worley(&self) -> Vec<(f64, usize)> {
+		let nearest = self.nearest_all(self.output != Output::F1);
+		let grid = if self.lookup != Lookup::Brute && self.output == Output::Edge { Some(self.grid()) } else { None };
+
+		nearest.par_iter().enumerate().map(|(index, n)| {
+			let (i, j) = (index as u32 % self.w, index as u32 / self.w);
+			let v = match self.output {
+				Output::Cells | Output::F1 => n.f1,
+				Output::F2 			=> n.f2,
+				Output::F2MinusF1 	=> n.f2 - n.f1,
+				Output::Edge if self.metric != Metric::Euclidean => (n.f2 - n.f1) / 2.,
+				Output::Edge 		=> match &grid {
+					Some(grid) => self.edge_distance_grid(grid, i, j, n),
+					None 	   => self.edge_distance(i, j, n),
+				},
+			};
+			(if v.is_finite() { v } else { 0. }, n.site)
+		}).collect()
+	}
+
+	pub(crate) fn worley_image(&self) -> Vec<u32> {
+		let noise = self.worley();
+		let max = noise.iter().map(|v| v.0).fold(0., f64::max);
+		let scale = if max > 0. { 1. / max } else { 0. };
+
+		noise.par_iter().map(|&(v, site)|
+			if self.colored { scale_color(self.points[site].color, v * scale) } else { gray((v * scale * 255.).round() as u8) }
+		).collect()
+	}
+}